@@ -0,0 +1,1623 @@
+//! Common types and utilities shared by the flow finders.
+
+use std::collections::{BTreeSet, HashSet};
+
+use anyhow::ensure;
+use fixedbitset::FixedBitSet;
+
+/// Unordered set of node indices.
+pub type Nodes = HashSet<usize>;
+
+/// Undirected graph represented as an adjacency list over nodes `0..n`.
+pub type Graph = Vec<Nodes>;
+
+/// Layer index of each node, counted from the output side.
+///
+/// Outputs live in layer `0` and a node in layer `k` is measured before
+/// every node in layer `k - 1`.
+pub type Layer = Vec<usize>;
+
+/// Ordered set of node indices, used when iteration order matters.
+pub type OrderedNodes = BTreeSet<usize>;
+
+/// Directed graph reusing the adjacency-list layout of [`Graph`]:
+/// `g[u]` holds the successors of `u` and is not required to be
+/// symmetric.
+pub type DiGraph = Vec<Nodes>;
+
+/// In-place set operations on `self`.
+pub trait InPlaceSetOp<T: Clone> {
+    /// Extends `self` with the elements of `other`.
+    fn union_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>;
+
+    /// Removes the elements of `other` from `self`.
+    fn difference_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>;
+}
+
+impl<T: Clone + Eq + std::hash::Hash> InPlaceSetOp<T> for HashSet<T> {
+    fn union_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>,
+    {
+        self.extend(other);
+    }
+
+    fn difference_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>,
+    {
+        other.into_iter().for_each(|x| {
+            self.remove(&x);
+        });
+    }
+}
+
+impl<T: Clone + Ord> InPlaceSetOp<T> for BTreeSet<T> {
+    fn union_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>,
+    {
+        self.extend(other);
+    }
+
+    fn difference_with<U>(&mut self, other: U)
+    where
+        U: IntoIterator<Item = T>,
+    {
+        other.into_iter().for_each(|x| {
+            self.remove(&x);
+        });
+    }
+}
+
+/// Computes the odd neighborhood of `kset`.
+///
+/// A node is in the odd neighborhood iff it is adjacent to an odd number
+/// of nodes in `kset`.
+pub fn odd_neighbors(g: &Graph, kset: &Nodes) -> Nodes {
+    let mut work = Nodes::new();
+    for &k in kset {
+        for &v in &g[k] {
+            if !work.insert(v) {
+                work.remove(&v);
+            }
+        }
+    }
+    work
+}
+
+/// Packs the adjacency list into one bitset row per node.
+///
+/// Row `u` has bit `v` set iff `u` and `v` are adjacent, ready for
+/// GF(2) linear algebra over the graph, e.g. with
+/// [`crate::gf2_linalg::GF2Solver`].
+pub fn adjacency_bitsets(g: &Graph) -> Vec<FixedBitSet> {
+    let n = g.len();
+    g.iter()
+        .map(|gu| {
+            let mut row = FixedBitSet::with_capacity(n);
+            gu.iter().for_each(|&v| row.insert(v));
+            row
+        })
+        .collect()
+}
+
+/// Cached bitset adjacency of one graph, for repeated neighborhood
+/// queries.
+///
+/// [`odd_neighbors`] walks hash sets on every call; when one graph is
+/// queried many times — focusing corrections, verifying flows,
+/// re-layering — XORing precomputed rows is much faster. Build once
+/// with [`AdjacencyBits::new`] and query away.
+#[derive(Clone, Debug)]
+pub struct AdjacencyBits {
+    rows: Vec<FixedBitSet>,
+}
+
+impl AdjacencyBits {
+    /// Packs the adjacency list of `g` into bitset rows.
+    #[must_use]
+    pub fn new(g: &Graph) -> Self {
+        Self {
+            rows: adjacency_bitsets(g),
+        }
+    }
+
+    /// Number of nodes of the underlying graph.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether the underlying graph has no nodes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Adjacency row of `u`: bit `v` is set iff `u` and `v` are
+    /// adjacent.
+    #[must_use]
+    pub fn row(&self, u: usize) -> &FixedBitSet {
+        &self.rows[u]
+    }
+
+    /// Computes the odd neighborhood of a node set.
+    ///
+    /// Same result as [`odd_neighbors`], obtained as the XOR of the
+    /// rows of `kset`.
+    #[must_use]
+    pub fn odd_neighbors(&self, kset: &Nodes) -> Nodes {
+        let mut acc = FixedBitSet::with_capacity(self.rows.len());
+        for &u in kset {
+            acc ^= &self.rows[u];
+        }
+        acc.ones().collect()
+    }
+}
+
+/// Computes the symmetric difference `N(u) △ N(v)` of two
+/// neighborhoods, excluding `u` and `v` themselves.
+///
+/// When `u` and `v` are adjacent, each appears in the other's
+/// neighborhood but not in its own; both are dropped from the result.
+pub fn neighborhood_symdiff(g: &Graph, u: usize, v: usize) -> Nodes {
+    let mut work: Nodes = g[u].symmetric_difference(&g[v]).copied().collect();
+    work.remove(&u);
+    work.remove(&v);
+    work
+}
+
+/// Builds the symmetric adjacency structure of an `n`-node graph from
+/// an edge list.
+///
+/// Duplicate edges are accepted idempotently; self-loops and endpoints
+/// outside `0..n` are rejected. The result always passes `check_graph`.
+pub fn graph_from_edges(n: usize, edges: &[(usize, usize)]) -> anyhow::Result<Graph> {
+    let mut g = vec![Nodes::new(); n];
+    for &(u, v) in edges {
+        ensure!(u < n && v < n, "edge ({u}, {v}) out of range");
+        ensure!(u != v, "self-loop detected: {u}");
+        g[u].insert(v);
+        g[v].insert(u);
+    }
+    Ok(g)
+}
+
+/// Dense remapping of arbitrary `u64` node labels onto `0..n`.
+///
+/// Labels get indices in increasing order, so the remap is
+/// deterministic. Built by [`relabel_graph`]; the helpers translate
+/// node sets, correction functions, and layers between the labeled and
+/// dense worlds, sparing callers a hand-maintained bijection.
+pub struct Relabeling {
+    /// Dense index of each label.
+    pub to_dense: std::collections::HashMap<u64, usize>,
+    /// Label of each dense index.
+    pub to_label: Vec<u64>,
+}
+
+impl Relabeling {
+    /// Translates a set of labels into dense node indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label is unknown.
+    pub fn dense_nodes(&self, labels: &HashSet<u64>) -> Nodes {
+        labels.iter().map(|l| self.to_dense[l]).collect()
+    }
+
+    /// Translates a dense correction function back to labels.
+    pub fn label_flow(
+        &self,
+        f: &std::collections::HashMap<usize, Nodes>,
+    ) -> std::collections::HashMap<u64, HashSet<u64>> {
+        f.iter()
+            .map(|(&u, fu)| {
+                (
+                    self.to_label[u],
+                    fu.iter().map(|&v| self.to_label[v]).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Translates a dense layer vector back to a per-label map.
+    pub fn label_layer(&self, layer: &Layer) -> std::collections::HashMap<u64, usize> {
+        layer
+            .iter()
+            .enumerate()
+            .map(|(u, &k)| (self.to_label[u], k))
+            .collect()
+    }
+}
+
+/// Builds the dense adjacency structure of a graph with arbitrary
+/// `u64` node labels.
+///
+/// Every neighbor must itself be a key of `g`. The returned graph is
+/// ready for the finders; run one on it and use the [`Relabeling`] to
+/// translate the result back.
+pub fn relabel_graph(
+    g: &std::collections::HashMap<u64, HashSet<u64>>,
+) -> anyhow::Result<(Graph, Relabeling)> {
+    let mut to_label: Vec<u64> = g.keys().copied().collect();
+    to_label.sort_unstable();
+    let to_dense: std::collections::HashMap<u64, usize> = to_label
+        .iter()
+        .enumerate()
+        .map(|(i, &l)| (l, i))
+        .collect();
+    let mut dense = vec![Nodes::new(); to_label.len()];
+    for (l, neighbors) in g {
+        for v in neighbors {
+            let Some(&j) = to_dense.get(v) else {
+                anyhow::bail!("neighbor label not a node: {v}");
+            };
+            dense[to_dense[l]].insert(j);
+        }
+    }
+    Ok((dense, Relabeling { to_dense, to_label }))
+}
+
+/// Checks the graph invariants required by all flow finders.
+///
+/// The adjacency list must be symmetric, free of self-loops, and refer
+/// only to nodes in `0..g.len()`, and `iset`/`oset` must be subsets of
+/// the node set.
+pub fn check_graph(g: &Graph, iset: &Nodes, oset: &Nodes) -> anyhow::Result<()> {
+    let n = g.len();
+    for (u, gu) in g.iter().enumerate() {
+        for &v in gu {
+            ensure!(v < n, "node index out of range: {v}");
+            ensure!(u != v, "self-loop detected: {u}");
+            ensure!(g[v].contains(&u), "adjacency list is not symmetric: {u}-{v}");
+        }
+    }
+    ensure!(iset.iter().all(|&u| u < n), "iset is not a subset of the nodes");
+    ensure!(oset.iter().all(|&u| u < n), "oset is not a subset of the nodes");
+    Ok(())
+}
+
+/// Checks the graph invariants alone, without input or output sets.
+///
+/// Defensive entry point for graphs assembled directly in Rust, where
+/// nothing else validates the structure: confirms symmetry, the
+/// absence of self-loops, and in-range neighbor indices by delegating
+/// to [`check_graph`] with empty sets. A malformed adjacency list is
+/// reported here instead of silently producing wrong answers in the
+/// finders.
+pub fn validate_graph(g: &Graph) -> anyhow::Result<()> {
+    check_graph(g, &Nodes::new(), &Nodes::new())
+}
+
+/// Computes the tensor (categorical) product of two graphs.
+///
+/// Nodes `(i, j)` and `(k, l)` are adjacent iff `i`-`k` and `j`-`l`
+/// both are. Returns the product graph together with the map from
+/// node pairs to product node indices.
+pub fn tensor_product(
+    g1: &Graph,
+    g2: &Graph,
+) -> (Graph, std::collections::HashMap<(usize, usize), usize>) {
+    product_graph(g1, g2, |adj1, eq1, adj2, eq2| {
+        let _ = (eq1, eq2);
+        adj1 && adj2
+    })
+}
+
+/// Computes the strong product of two graphs.
+///
+/// Nodes `(i, j)` and `(k, l)` are adjacent iff each coordinate is
+/// adjacent or equal, and the pairs differ.
+pub fn strong_product(
+    g1: &Graph,
+    g2: &Graph,
+) -> (Graph, std::collections::HashMap<(usize, usize), usize>) {
+    product_graph(g1, g2, |adj1, eq1, adj2, eq2| {
+        (adj1 || eq1) && (adj2 || eq2)
+    })
+}
+
+/// Shared worker for graph products: `rule` decides adjacency of two
+/// distinct product nodes from coordinate-wise adjacency and equality.
+fn product_graph(
+    g1: &Graph,
+    g2: &Graph,
+    rule: impl Fn(bool, bool, bool, bool) -> bool,
+) -> (Graph, std::collections::HashMap<(usize, usize), usize>) {
+    let (n1, n2) = (g1.len(), g2.len());
+    let index: std::collections::HashMap<(usize, usize), usize> = (0..n1)
+        .flat_map(|i| (0..n2).map(move |j| ((i, j), i * n2 + j)))
+        .collect();
+    let mut g = vec![Nodes::new(); n1 * n2];
+    for i in 0..n1 {
+        for j in 0..n2 {
+            for k in 0..n1 {
+                for l in 0..n2 {
+                    if (i, j) == (k, l) {
+                        continue;
+                    }
+                    if rule(g1[i].contains(&k), i == k, g2[j].contains(&l), j == l) {
+                        g[index[&(i, j)]].insert(index[&(k, l)]);
+                    }
+                }
+            }
+        }
+    }
+    (g, index)
+}
+
+/// Extracts the precedence DAG of a flow as a directed graph.
+///
+/// There is an edge `u -> w` iff `w` must be measured after `u`, i.e.
+/// `w` lies in the correction set of `u` or in its odd neighborhood.
+pub fn flow_to_graph(g: &Graph, f: &std::collections::HashMap<usize, Nodes>) -> DiGraph {
+    let mut dag = vec![Nodes::new(); g.len()];
+    for (&u, fu) in f {
+        let mut deps = odd_neighbors(g, fu);
+        deps.union_with(fu.iter().copied());
+        deps.remove(&u);
+        dag[u] = deps;
+    }
+    dag
+}
+
+/// Lists the precedence edges of a flow as explicit pairs.
+///
+/// The pair `(u, w)` means `u` must be measured before `w`; these are
+/// the edges of [`flow_to_graph`], flattened and sorted for direct
+/// consumption by schedulers.
+pub fn precedence_edges(
+    g: &Graph,
+    f: &std::collections::HashMap<usize, Nodes>,
+) -> Vec<(usize, usize)> {
+    let dag = flow_to_graph(g, f);
+    let mut edges: Vec<(usize, usize)> = dag
+        .iter()
+        .enumerate()
+        .flat_map(|(u, deps)| deps.iter().map(move |&w| (u, w)))
+        .collect();
+    edges.sort_unstable();
+    edges
+}
+
+/// Enumerates the maximal antichains of a flow's partial order.
+///
+/// Two nodes are comparable iff one reaches the other in the
+/// precedence DAG of [`flow_to_graph`]; a maximal antichain is an
+/// inclusion-maximal set of pairwise incomparable nodes, i.e. a
+/// maximal group of measurements that could run concurrently. The
+/// enumeration is exponential in the worst case and stops once `cap`
+/// antichains have been collected.
+pub fn all_maximal_antichains(
+    g: &Graph,
+    f: &std::collections::HashMap<usize, Nodes>,
+    cap: usize,
+) -> Vec<Nodes> {
+    let n = g.len();
+    let dag = flow_to_graph(g, f);
+    // Transitive closure: reach[u] holds every strict descendant of u.
+    let mut reach = vec![Nodes::new(); n];
+    for start in 0..n {
+        let mut stack: Vec<usize> = dag[start].iter().copied().collect();
+        while let Some(u) = stack.pop() {
+            if reach[start].insert(u) {
+                stack.extend(dag[u].iter().copied());
+            }
+        }
+    }
+    let incomparable = |u: usize, v: usize| !reach[u].contains(&v) && !reach[v].contains(&u);
+    // Bron-Kerbosch over the incomparability graph: maximal antichains
+    // are exactly its maximal cliques.
+    fn extend(
+        current: &mut Vec<usize>,
+        mut cand: Vec<usize>,
+        mut excl: Vec<usize>,
+        incomparable: &dyn Fn(usize, usize) -> bool,
+        cap: usize,
+        out: &mut Vec<Nodes>,
+    ) {
+        if out.len() >= cap {
+            return;
+        }
+        if cand.is_empty() && excl.is_empty() {
+            out.push(current.iter().copied().collect());
+            return;
+        }
+        while let Some(v) = cand.pop() {
+            if out.len() >= cap {
+                return;
+            }
+            current.push(v);
+            let ncand = cand.iter().copied().filter(|&w| incomparable(v, w)).collect();
+            let nexcl = excl.iter().copied().filter(|&w| incomparable(v, w)).collect();
+            extend(current, ncand, nexcl, incomparable, cap, out);
+            current.pop();
+            excl.push(v);
+        }
+    }
+    let mut antichains = Vec::new();
+    extend(
+        &mut Vec::new(),
+        (0..n).collect(),
+        Vec::new(),
+        &incomparable,
+        cap,
+        &mut antichains,
+    );
+    antichains
+}
+
+/// Extracts one longest chain of dependent corrections.
+///
+/// Walks the precedence DAG of [`flow_to_graph`] and returns a longest
+/// path as a node sequence in measurement order. Ties are broken
+/// towards the smaller node index. `layer` must be consistent with the
+/// flow and is used to process dependents first.
+pub fn critical_path(
+    g: &Graph,
+    f: &std::collections::HashMap<usize, Nodes>,
+    layer: &Layer,
+) -> Vec<usize> {
+    let n = g.len();
+    let dag = flow_to_graph(g, f);
+    // Layer 0 is measured last, so increasing layer visits every node
+    // after all of its successors.
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_unstable_by_key(|&u| layer[u]);
+    // Length of the longest path starting at each node, and the
+    // successor continuing it.
+    let mut best: Vec<(usize, Option<usize>)> = vec![(1, None); n];
+    for &u in &order {
+        let mut succs: Vec<usize> = dag[u].iter().copied().collect();
+        succs.sort_unstable();
+        for w in succs {
+            if best[w].0 + 1 > best[u].0 {
+                best[u] = (best[w].0 + 1, Some(w));
+            }
+        }
+    }
+    let start = (0..n).max_by_key(|&u| (best[u].0, std::cmp::Reverse(u)));
+    let mut path = Vec::new();
+    let mut cursor = start;
+    while let Some(u) = cursor {
+        path.push(u);
+        cursor = best[u].1;
+    }
+    path
+}
+
+/// Merges consecutive layers with no correction crossing them.
+///
+/// Scans the layers from the outputs upward and collapses a layer into
+/// its predecessor whenever no precedence edge of [`flow_to_graph`]
+/// connects the two, producing a shallower layering that orders the
+/// same dependencies. Whole layers are merged or kept; nodes never
+/// move individually. The output layer is never merged into.
+pub fn merge_independent_layers(
+    g: &Graph,
+    f: &std::collections::HashMap<usize, Nodes>,
+    layer: &Layer,
+) -> Layer {
+    let dag = flow_to_graph(g, f);
+    let depth = layer.iter().copied().max().unwrap_or(0);
+    let mut buckets = vec![Vec::new(); depth + 1];
+    for (u, &k) in layer.iter().enumerate() {
+        buckets[k].push(u);
+    }
+    let mut merged = vec![0; g.len()];
+    // Nodes of the layer currently being grown, and its new index.
+    let mut current: Nodes = buckets[0].iter().copied().collect();
+    let mut idx = 0;
+    for bucket in &buckets[1..] {
+        let mergeable = idx > 0
+            && bucket
+                .iter()
+                .all(|&u| dag[u].iter().all(|w| !current.contains(w)));
+        if !mergeable {
+            idx += 1;
+            current.clear();
+        }
+        for &u in bucket {
+            merged[u] = idx;
+        }
+        current.extend(bucket);
+    }
+    merged
+}
+
+/// Counts the connected components of `g`.
+pub fn connected_components(g: &Graph) -> usize {
+    let n = g.len();
+    let mut seen = vec![false; n];
+    let mut components = 0;
+    for start in 0..n {
+        if seen[start] {
+            continue;
+        }
+        components += 1;
+        let mut stack = vec![start];
+        seen[start] = true;
+        while let Some(u) = stack.pop() {
+            for &v in &g[u] {
+                if !seen[v] {
+                    seen[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+    }
+    components
+}
+
+/// Computes the cycle rank (first Betti number) of `g`: the number of
+/// independent cycles, `edges - nodes + components`.
+pub fn cycle_rank(g: &Graph) -> usize {
+    let edges = g.iter().map(Nodes::len).sum::<usize>() / 2;
+    edges + connected_components(g) - g.len()
+}
+
+/// Identifies node `b` with node `a`, merging their neighborhoods.
+///
+/// The merged node keeps the input/output status of either original,
+/// self-loops arising from an `a`-`b` edge are dropped, and remaining
+/// nodes are relabeled to keep indices contiguous. Returns the new
+/// graph, input and output sets, and the old-to-new relabeling (with
+/// `relabel[b] == relabel[a]`).
+///
+/// # Panics
+///
+/// Panics if `a` and `b` are equal or out of range.
+pub fn identify_nodes(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    a: usize,
+    b: usize,
+) -> (Graph, Nodes, Nodes, Vec<usize>) {
+    let n = g.len();
+    assert!(a < n && b < n && a != b, "invalid node pair: {a}, {b}");
+    let relabel: Vec<usize> = (0..n)
+        .map(|u| {
+            let u = if u == b { a } else { u };
+            u - usize::from(u > b)
+        })
+        .collect();
+    let mut merged = vec![Nodes::new(); n - 1];
+    for (u, gu) in g.iter().enumerate() {
+        for &v in gu {
+            let (nu, nv) = (relabel[u], relabel[v]);
+            if nu != nv {
+                merged[nu].insert(nv);
+                merged[nv].insert(nu);
+            }
+        }
+    }
+    let project = |set: &Nodes| set.iter().map(|&u| relabel[u]).collect();
+    (merged, project(iset), project(oset), relabel)
+}
+
+/// Collapses each class of `partition` into a single node.
+///
+/// Generalizes [`identify_nodes`] to a full partition: class `k`
+/// becomes quotient node `k`, neighborhoods are unioned, and edges
+/// internal to a class are dropped. Input/output status is inherited
+/// from any member. Returns the quotient graph, input and output
+/// sets, and the lift map from quotient nodes back to their classes.
+///
+/// # Panics
+///
+/// Panics if `partition` is not a partition of `0..g.len()` or has an
+/// empty class.
+pub fn quotient(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    partition: &[Nodes],
+) -> (Graph, Nodes, Nodes, Vec<Nodes>) {
+    let n = g.len();
+    let mut class = vec![usize::MAX; n];
+    for (k, members) in partition.iter().enumerate() {
+        assert!(!members.is_empty(), "empty class: {k}");
+        for &u in members {
+            assert!(u < n, "node index out of range: {u}");
+            assert!(class[u] == usize::MAX, "node in two classes: {u}");
+            class[u] = k;
+        }
+    }
+    assert!(class.iter().all(|&k| k != usize::MAX), "partition does not cover the nodes");
+    let mut merged = vec![Nodes::new(); partition.len()];
+    for (u, gu) in g.iter().enumerate() {
+        for &v in gu {
+            let (ku, kv) = (class[u], class[v]);
+            if ku != kv {
+                merged[ku].insert(kv);
+                merged[kv].insert(ku);
+            }
+        }
+    }
+    let project = |set: &Nodes| set.iter().map(|&u| class[u]).collect();
+    (merged, project(iset), project(oset), partition.to_vec())
+}
+
+/// Builds a 2D grid cluster state with default I/O columns.
+///
+/// Node `(r, c)` gets index `r * cols + c` and is wired to its right
+/// and lower neighbors. With `periodic`, the row direction wraps
+/// around, turning the grid into a cylinder; the column direction
+/// stays open so the defaults — inputs in the first column, outputs in
+/// the last — keep their roles. The wrap edge is skipped for fewer
+/// than three rows, where it would duplicate an existing edge.
+///
+/// # Panics
+///
+/// Panics if `rows` or `cols` is zero.
+pub fn grid_graph(rows: usize, cols: usize, periodic: bool) -> (Graph, Nodes, Nodes) {
+    assert!(rows > 0 && cols > 0, "empty grid");
+    let index = |r: usize, c: usize| r * cols + c;
+    let mut g = vec![Nodes::new(); rows * cols];
+    let mut add = |u: usize, v: usize| {
+        g[u].insert(v);
+        g[v].insert(u);
+    };
+    for r in 0..rows {
+        for c in 0..cols {
+            if c + 1 < cols {
+                add(index(r, c), index(r, c + 1));
+            }
+            if r + 1 < rows {
+                add(index(r, c), index(r + 1, c));
+            } else if periodic && rows > 2 {
+                add(index(r, c), index(0, c));
+            }
+        }
+    }
+    let iset = (0..rows).map(|r| index(r, 0)).collect();
+    let oset = (0..rows).map(|r| index(r, cols - 1)).collect();
+    (g, iset, oset)
+}
+
+/// Splits the internal node `v` into an input copy and an output copy.
+///
+/// The input copy keeps the index `v` and joins `iset`; the output
+/// copy is appended as a fresh node, returned last, and joins `oset`.
+/// Neighbors in `to_output` are rewired to the output copy and the
+/// remaining neighbors stay on the input copy; the two copies are not
+/// adjacent. Returns the new graph, input and output sets, and the
+/// index of the output copy.
+///
+/// # Panics
+///
+/// Panics if `v` is out of range or not internal, or if `to_output` is
+/// not a subset of its neighborhood.
+pub fn split_node(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    v: usize,
+    to_output: &Nodes,
+) -> (Graph, Nodes, Nodes, usize) {
+    let n = g.len();
+    assert!(v < n, "node index out of range: {v}");
+    assert!(
+        !iset.contains(&v) && !oset.contains(&v),
+        "node is not internal: {v}"
+    );
+    assert!(
+        to_output.is_subset(&g[v]),
+        "to_output is not a subset of the neighborhood"
+    );
+    let mut split = g.clone();
+    split.push(Nodes::new());
+    for &w in to_output {
+        split[v].remove(&w);
+        split[w].remove(&v);
+        split[w].insert(n);
+        split[n].insert(w);
+    }
+    let mut niset = iset.clone();
+    niset.insert(v);
+    let mut noset = oset.clone();
+    noset.insert(n);
+    (split, niset, noset, n)
+}
+
+/// One-qubit Clifford correction relating a stabilizer state to its
+/// graph-state representative, as returned by [`from_stabilizers`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LocalClifford {
+    /// No correction.
+    I,
+    /// Hadamard.
+    H,
+    /// Phase gate.
+    S,
+    /// Phase gate after Hadamard.
+    HS,
+}
+
+/// Reduces a stabilizer-group description to a graph plus local
+/// Cliffords.
+///
+/// Each generator is given as its X and Z supports (signs are
+/// ignored). When the state is graph-state-equivalent under Hadamard
+/// and phase corrections, returns the graph together with the per-
+/// qubit correction mapping the given group onto the graph-state
+/// generators `X_v Z_N(v)`; otherwise `None`.
+pub fn from_stabilizers(generators: &[(Nodes, Nodes)]) -> Option<(Graph, Vec<LocalClifford>)> {
+    let n = generators.len();
+    let mut rows: Vec<(FixedBitSet, FixedBitSet)> = generators
+        .iter()
+        .map(|(xs, zs)| {
+            let mut x = FixedBitSet::with_capacity(n);
+            let mut z = FixedBitSet::with_capacity(n);
+            xs.iter().for_each(|&q| x.insert(q));
+            zs.iter().for_each(|&q| z.insert(q));
+            (x, z)
+        })
+        .collect();
+    let mut cliffords = vec![LocalClifford::I; n];
+    // Bring the X block to the identity, applying a Hadamard wherever
+    // a pivot is only available in the Z block.
+    for j in 0..n {
+        let pivot = match (j..n).find(|&i| rows[i].0.contains(j)) {
+            Some(i) => i,
+            None => {
+                let i = (j..n).find(|&i| rows[i].1.contains(j))?;
+                for (x, z) in &mut rows {
+                    let (xb, zb) = (x.contains(j), z.contains(j));
+                    x.set(j, zb);
+                    z.set(j, xb);
+                }
+                cliffords[j] = LocalClifford::H;
+                i
+            }
+        };
+        rows.swap(j, pivot);
+        let (xj, zj) = rows[j].clone();
+        for (i, (x, z)) in rows.iter_mut().enumerate() {
+            if i != j && x.contains(j) {
+                *x ^= &xj;
+                *z ^= &zj;
+            }
+        }
+    }
+    // The Z block must now be an adjacency matrix; a phase gate clears
+    // a diagonal entry.
+    for (j, clifford) in cliffords.iter_mut().enumerate() {
+        if rows[j].1.contains(j) {
+            rows[j].1.remove(j);
+            *clifford = match clifford {
+                LocalClifford::I => LocalClifford::S,
+                LocalClifford::H => LocalClifford::HS,
+                _ => unreachable!("set at most once per qubit"),
+            };
+        }
+    }
+    let g: Graph = rows
+        .iter()
+        .map(|(_, z)| z.ones().collect::<Nodes>())
+        .collect();
+    check_graph(&g, &Nodes::new(), &Nodes::new()).ok()?;
+    Some((g, cliffords))
+}
+
+/// Computes the simple-graph complement of `g`.
+///
+/// Two distinct nodes are adjacent in the result iff they are not
+/// adjacent in `g`; no self-loops are produced.
+pub fn complement(g: &Graph) -> Graph {
+    let n = g.len();
+    (0..n)
+        .map(|u| (0..n).filter(|&v| v != u && !g[u].contains(&v)).collect())
+        .collect()
+}
+
+/// Packs a flow's correction sets into compressed-sparse-row form.
+///
+/// Returns `(offsets, targets)` with `offsets.len() == n + 1`: the
+/// correction set of node `u` is `targets[offsets[u]..offsets[u + 1]]`,
+/// sorted ascending, and empty for uncorrected nodes.
+pub fn flow_to_csr(
+    f: &std::collections::HashMap<usize, Nodes>,
+    n: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut offsets = Vec::with_capacity(n + 1);
+    let mut targets = Vec::new();
+    offsets.push(0);
+    for u in 0..n {
+        if let Some(fu) = f.get(&u) {
+            let mut row: Vec<usize> = fu.iter().copied().collect();
+            row.sort_unstable();
+            targets.extend(row);
+        }
+        offsets.push(targets.len());
+    }
+    (offsets, targets)
+}
+
+/// Counts the nodes of each layer.
+///
+/// Entry `k` of the result is the number of nodes in layer `k`; the
+/// length is one past the deepest layer, or zero for an empty graph.
+pub fn layer_widths(layer: &Layer) -> Vec<usize> {
+    let Some(&depth) = layer.iter().max() else {
+        return Vec::new();
+    };
+    let mut widths = vec![0; depth + 1];
+    for &k in layer {
+        widths[k] += 1;
+    }
+    widths
+}
+
+/// Flattens a layering into a total measurement order.
+///
+/// Nodes are emitted from the highest layer down to the lowest, since
+/// deeper layers are measured first; within a layer, nodes come in
+/// increasing index order, so the result is deterministic. Outputs are
+/// not measured and come last, after any layer-0 stragglers of a
+/// partial layering.
+pub fn layer_to_order(layer: &Layer, oset: &Nodes) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..layer.len()).collect();
+    order.sort_by_key(|&u| (std::cmp::Reverse(layer[u]), oset.contains(&u), u));
+    order
+}
+
+/// Computes the size of the largest layer.
+///
+/// This is the maximum number of qubits measured simultaneously when
+/// the layers are executed in parallel; `0` for an empty graph.
+pub fn max_width(layer: &Layer) -> usize {
+    layer_widths(layer).into_iter().max().unwrap_or(0)
+}
+
+/// Re-expresses a correction function with deterministic ordering.
+///
+/// Correction sets are hash sets, so their serialized element order
+/// varies from run to run. The returned map is ordered by corrected
+/// node and each correction set becomes a sorted `Vec<usize>`, making
+/// run-to-run diffs and golden-file comparisons stable.
+pub fn sorted_corrections(
+    f: &std::collections::HashMap<usize, Nodes>,
+) -> std::collections::BTreeMap<usize, Vec<usize>> {
+    f.iter()
+        .map(|(&u, fu)| {
+            let mut row: Vec<usize> = fu.iter().copied().collect();
+            row.sort_unstable();
+            (u, row)
+        })
+        .collect()
+}
+
+/// Counts, per node, how many correction sets include it.
+///
+/// The fan-in complements the correction-set sizes (fan-out): nodes
+/// with a large fan-in receive classical feedforward from many
+/// measurements. Nodes in no correction set are absent from the map.
+pub fn correction_fan_in(
+    f: &std::collections::HashMap<usize, Nodes>,
+) -> std::collections::HashMap<usize, usize> {
+    let mut fan_in = std::collections::HashMap::new();
+    for fu in f.values() {
+        for &v in fu {
+            *fan_in.entry(v).or_default() += 1;
+        }
+    }
+    fan_in
+}
+
+/// Computes a cheap lower bound on the depth of any causal or
+/// generalized flow.
+///
+/// In those flows every measured node needs a corrected neighbor in a
+/// strictly lower layer, so a node's layer is at least its distance to
+/// the output set. The bound is the largest such distance over
+/// reachable nodes. It does not hold for Pauli flow: Pauli-measured
+/// nodes escape the ordering constraint, so a Pauli flow can be
+/// shallower than this bound (see [`delay_comparison`]). `iset` does
+/// not affect the bound and is taken for signature symmetry with the
+/// finders.
+pub fn depth_lower_bound(g: &Graph, iset: &Nodes, oset: &Nodes) -> usize {
+    let _ = iset;
+    let mut dist = vec![usize::MAX; g.len()];
+    let mut queue: std::collections::VecDeque<usize> = oset.iter().copied().collect();
+    for &o in oset {
+        dist[o] = 0;
+    }
+    while let Some(u) = queue.pop_front() {
+        for &v in &g[u] {
+            if dist[v] == usize::MAX {
+                dist[v] = dist[u] + 1;
+                queue.push_back(v);
+            }
+        }
+    }
+    dist.iter()
+        .filter(|&&d| d != usize::MAX)
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+/// Compares the depth of the gflow and Pauli flow of the same graph.
+///
+/// Runs both finders, interpreting each Pauli axis as its containing
+/// plane for the gflow side, and returns the two depths together with
+/// the per-node layer difference `gflow - pflow`. `None` if either
+/// finder fails.
+pub fn delay_comparison(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    pplane: &std::collections::HashMap<usize, crate::pflow::PPlane>,
+) -> Option<(usize, usize, Vec<i64>)> {
+    let plane = pplane.iter().map(|(&u, &p)| (u, p.plane())).collect();
+    let (_, glayer) = crate::gflow::find(g.clone(), iset.clone(), oset.clone(), plane)?;
+    let (_, player) =
+        crate::pflow::find(g.clone(), iset.clone(), oset.clone(), pplane.clone())?;
+    let gdepth = glayer.iter().copied().max().unwrap_or(0);
+    let pdepth = player.iter().copied().max().unwrap_or(0);
+    let diff = glayer
+        .iter()
+        .zip(&player)
+        .map(|(&gl, &pl)| gl as i64 - pl as i64)
+        .collect();
+    Some((gdepth, pdepth, diff))
+}
+
+/// Hashes the coarse structure of a flow for cheap deduplication.
+///
+/// The signature digests the sorted correction-set sizes, the layer
+/// histogram, and the depth. Structurally identical flows hash equal;
+/// different ones usually differ, so this is only a pre-filter before
+/// a real equivalence check.
+pub fn flow_signature(f: &std::collections::HashMap<usize, Nodes>, layer: &Layer) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut sizes: Vec<usize> = f.values().map(Nodes::len).collect();
+    sizes.sort_unstable();
+    let depth = layer.iter().copied().max().unwrap_or(0);
+    let mut histogram = vec![0usize; depth + 1];
+    for &k in layer {
+        histogram[k] += 1;
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    sizes.hash(&mut hasher);
+    histogram.hash(&mut hasher);
+    depth.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Estimates the number of bit operations a flow search will take.
+///
+/// Heuristic: in the worst case one round is run per measured node,
+/// each eliminating a matrix with a row per measured node and a column
+/// per potential corrector, at a cost proportional to the adjacency
+/// density. The result is not exact, only monotonic enough to
+/// prioritize jobs.
+pub fn estimate_cost(g: &Graph, iset: &Nodes, oset: &Nodes) -> u64 {
+    let n = g.len() as u64;
+    let measured = (g.len() - oset.len()) as u64;
+    let correctors = (g.len() - iset.len()) as u64;
+    let density = g.iter().map(|gu| gu.len() as u64).sum::<u64>().max(1);
+    measured * measured * correctors.max(1) + measured * density * n
+}
+
+/// Expresses a layering as absolute measurement times.
+///
+/// Nodes with an entry in `output_times` are scheduled at their given
+/// read-out time. Every other node in layer `k` is scheduled `k` layer
+/// durations before the earliest output, so that higher layers are
+/// measured strictly earlier.
+pub fn absolute_schedule(
+    layer: &Layer,
+    output_times: &std::collections::HashMap<usize, f64>,
+    layer_duration: f64,
+) -> std::collections::HashMap<usize, f64> {
+    let base = output_times
+        .values()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    layer
+        .iter()
+        .enumerate()
+        .map(|(u, &k)| {
+            let t = output_times
+                .get(&u)
+                .copied()
+                .unwrap_or(base - k as f64 * layer_duration);
+            (u, t)
+        })
+        .collect()
+}
+
+/// Checks that the initial layer assignment is consistent with `oset`:
+/// every output must sit in layer `0`.
+pub(crate) fn check_initial(layer: &Layer, oset: &Nodes) -> anyhow::Result<()> {
+    for &u in oset {
+        ensure!(
+            layer[u] == 0,
+            "initial layer is inconsistent with oset: {u}"
+        );
+    }
+    Ok(())
+}
+
+/// Checks that no correction set contains an input node.
+///
+/// The finders guarantee this by construction; the check is for flows
+/// obtained elsewhere. The reported error names the offending
+/// corrector and the correction set it appears in.
+pub fn check_no_input_in_corrections(
+    f: &std::collections::HashMap<usize, Nodes>,
+    iset: &Nodes,
+) -> anyhow::Result<()> {
+    for (&u, fu) in f {
+        for &v in fu {
+            ensure!(
+                !iset.contains(&v),
+                "correction set of {u} contains the input node {v}"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `map` assigns a value to exactly the measured nodes,
+/// i.e. its keys are `vset \ oset`. On mismatch the error lists every
+/// missing and extra node, so a misplaced plane map is diagnosed in
+/// one round trip.
+pub(crate) fn check_domain<T>(
+    map: &std::collections::HashMap<usize, T>,
+    vset: &Nodes,
+    oset: &Nodes,
+) -> anyhow::Result<()> {
+    let mut missing: Vec<usize> = vset
+        .iter()
+        .filter(|u| !oset.contains(u) && !map.contains_key(u))
+        .copied()
+        .collect();
+    missing.sort_unstable();
+    let mut extra: Vec<usize> = map
+        .keys()
+        .filter(|u| oset.contains(u) || !vset.contains(u))
+        .copied()
+        .collect();
+    extra.sort_unstable();
+    ensure!(
+        missing.is_empty() && extra.is_empty(),
+        "domain mismatch: missing nodes {missing:?}, extra nodes {extra:?}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, nodeset};
+
+    #[test]
+    fn test_check_domain_lists_mismatch() {
+        // 1 misses an assignment while the output 2 has a spurious one;
+        // both show up in a single error.
+        let map = std::collections::HashMap::from([(0, ()), (2, ())]);
+        let err = check_domain(&map, &nodeset([0, 1, 2]), &nodeset([2])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "domain mismatch: missing nodes [1], extra nodes [2]"
+        );
+    }
+
+    #[test]
+    fn test_odd_neighbors() {
+        // 0 - 1 - 2
+        //     |
+        //     3
+        let g = test_utils::graph(4, &[(0, 1), (1, 2), (1, 3)]);
+        assert_eq!(odd_neighbors(&g, &nodeset([1])), nodeset([0, 2, 3]));
+        assert_eq!(odd_neighbors(&g, &nodeset([0, 2])), nodeset([]));
+        assert_eq!(odd_neighbors(&g, &nodeset([0, 3])), nodeset([]));
+        assert_eq!(odd_neighbors(&g, &nodeset([1, 2])), nodeset([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_tensor_product() {
+        // K2 x K2 is a perfect matching on four nodes.
+        let k2 = test_utils::graph(2, &[(0, 1)]);
+        let (g, index) = tensor_product(&k2, &k2);
+        assert_eq!(g.len(), 4);
+        assert_eq!(g[index[&(0, 0)]], nodeset([index[&(1, 1)]]));
+        assert_eq!(g[index[&(0, 1)]], nodeset([index[&(1, 0)]]));
+        assert!(check_graph(&g, &nodeset([]), &nodeset([])).is_ok());
+    }
+
+    #[test]
+    fn test_strong_product() {
+        // K2 boxtimes K2 is K4.
+        let k2 = test_utils::graph(2, &[(0, 1)]);
+        let (g, _) = strong_product(&k2, &k2);
+        assert!(g.iter().enumerate().all(|(u, gu)| gu.len() == 3 && !gu.contains(&u)));
+        assert!(check_graph(&g, &nodeset([]), &nodeset([])).is_ok());
+    }
+
+    #[test]
+    fn test_tensor_product_line() {
+        // P2 x P3: node (0, 1) is adjacent to (1, 0) and (1, 2).
+        let p2 = test_utils::graph(2, &[(0, 1)]);
+        let p3 = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let (g, index) = tensor_product(&p2, &p3);
+        assert_eq!(
+            g[index[&(0, 1)]],
+            nodeset([index[&(1, 0)], index[&(1, 2)]])
+        );
+    }
+
+    #[test]
+    fn test_flow_to_graph() {
+        // 0 - 1 - 2 with f(0) = {1}, f(1) = {2}.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let f = [(0, nodeset([1])), (1, nodeset([2]))].into_iter().collect();
+        let dag = flow_to_graph(&g, &f);
+        assert_eq!(dag[0], nodeset([1, 2]));
+        assert_eq!(dag[1], nodeset([2]));
+        assert_eq!(dag[2], nodeset([]));
+    }
+
+    #[test]
+    fn test_graph_from_edges() {
+        // Duplicates collapse; the result matches the test helper.
+        let g = graph_from_edges(3, &[(0, 1), (1, 2), (1, 0)]).unwrap();
+        assert_eq!(g, test_utils::graph(3, &[(0, 1), (1, 2)]));
+        assert!(graph_from_edges(2, &[(0, 0)]).is_err());
+        assert!(graph_from_edges(2, &[(0, 2)]).is_err());
+    }
+
+    #[test]
+    fn test_relabel_graph() {
+        // Labeled line 10 - 20 - 30 runs through the causal flow finder
+        // and translates back.
+        let g = [
+            (10, HashSet::from([20])),
+            (20, HashSet::from([10, 30])),
+            (30, HashSet::from([20])),
+        ]
+        .into_iter()
+        .collect();
+        let (dense, map) = relabel_graph(&g).unwrap();
+        let iset = map.dense_nodes(&HashSet::from([10]));
+        let oset = map.dense_nodes(&HashSet::from([30]));
+        let (f, layer) = crate::flow::find(dense, iset, oset).unwrap();
+        let f: std::collections::HashMap<usize, Nodes> =
+            f.into_iter().map(|(u, v)| (u, Nodes::from([v]))).collect();
+        let labeled = map.label_flow(&f);
+        assert_eq!(labeled[&10], HashSet::from([20]));
+        assert_eq!(labeled[&20], HashSet::from([30]));
+        let layers = map.label_layer(&layer);
+        assert_eq!(layers[&10], 2);
+        assert_eq!(layers[&30], 0);
+    }
+
+    #[test]
+    fn test_relabel_graph_unknown_label() {
+        let g = [(10, HashSet::from([99]))].into_iter().collect();
+        assert!(relabel_graph(&g).is_err());
+    }
+
+    #[test]
+    fn test_precedence_edges() {
+        // Same flow as `test_flow_to_graph`, flattened.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let f = [(0, nodeset([1])), (1, nodeset([2]))].into_iter().collect();
+        assert_eq!(precedence_edges(&g, &f), vec![(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn test_adjacency_bitsets() {
+        // 0 - 1 - 2
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let rows = adjacency_bitsets(&g);
+        let ones: Vec<Vec<usize>> = rows.iter().map(|row| row.ones().collect()).collect();
+        assert_eq!(ones, vec![vec![1], vec![0, 2], vec![1]]);
+    }
+
+    #[test]
+    fn test_adjacency_bits() {
+        let g = test_utils::graph(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+        let bits = AdjacencyBits::new(&g);
+        assert_eq!(bits.len(), 4);
+        assert!(!bits.is_empty());
+        assert_eq!(bits.row(0).ones().collect::<Vec<_>>(), vec![1, 3]);
+        // Matches the hash-set implementation on every subset.
+        for mask in 0u32..16 {
+            let kset: Nodes = (0..4).filter(|v| mask & (1 << v) != 0).collect();
+            assert_eq!(bits.odd_neighbors(&kset), odd_neighbors(&g, &kset));
+        }
+    }
+
+    #[test]
+    fn test_neighborhood_symdiff() {
+        // 0 - 1 - 2
+        //     |
+        //     3 - 2
+        let g = test_utils::graph(4, &[(0, 1), (1, 2), (1, 3), (3, 2)]);
+        // N(0) = {1}, N(2) = {1, 3}: the shared 1 cancels.
+        assert_eq!(neighborhood_symdiff(&g, 0, 2), nodeset([3]));
+        // Adjacent pair: N(1) = {0, 2, 3}, N(3) = {1, 2}; 1 and 3 are
+        // dropped from the result.
+        assert_eq!(neighborhood_symdiff(&g, 1, 3), nodeset([0]));
+    }
+
+    #[test]
+    fn test_quotient() {
+        // Two disjoint paths 0-2-4 and 1-3-5, symmetric under swapping
+        // them; the quotient is a single path of the three classes.
+        let g = test_utils::graph(6, &[(0, 2), (2, 4), (1, 3), (3, 5)]);
+        let partition = vec![nodeset([0, 1]), nodeset([2, 3]), nodeset([4, 5])];
+        let (q, qiset, qoset, lift) =
+            quotient(&g, &nodeset([0, 1]), &nodeset([4, 5]), &partition);
+        assert_eq!(q, test_utils::graph(3, &[(0, 1), (1, 2)]));
+        assert_eq!(qiset, nodeset([0]));
+        assert_eq!(qoset, nodeset([2]));
+        assert_eq!(lift, partition);
+        // A flow on the quotient lifts along the symmetry: pairing the
+        // sorted members of each class with those of its corrector's
+        // class reproduces the flow of the original graph.
+        let (qf, _) = crate::flow::find(q, qiset, qoset).unwrap();
+        let mut lifted = std::collections::HashMap::new();
+        for (k, members) in lift.iter().enumerate() {
+            let Some(&target) = qf.get(&k) else { continue };
+            let sorted = |set: &Nodes| {
+                let mut v: Vec<usize> = set.iter().copied().collect();
+                v.sort_unstable();
+                v
+            };
+            for (u, v) in sorted(members).into_iter().zip(sorted(&lift[target])) {
+                lifted.insert(u, v);
+            }
+        }
+        let (f, _) = crate::flow::find(g, nodeset([0, 1]), nodeset([4, 5])).unwrap();
+        assert_eq!(lifted, f);
+    }
+
+    #[test]
+    fn test_grid_graph_periodic() {
+        // 3 x 2 cylinder: rows wrap, columns stay open.
+        let (g, iset, oset) = grid_graph(3, 2, true);
+        assert_eq!(iset, nodeset([0, 2, 4]));
+        assert_eq!(oset, nodeset([1, 3, 5]));
+        // Wrap-around edges between the first and last rows.
+        assert!(g[0].contains(&4));
+        assert!(g[1].contains(&5));
+        assert_eq!(g[0], nodeset([1, 2, 4]));
+        // The cylinder feeds straight into the causal flow finder.
+        let (f, layer) = crate::flow::find(g, iset, oset).unwrap();
+        assert_eq!(f[&0], 1);
+        assert_eq!(f[&2], 3);
+        assert_eq!(f[&4], 5);
+        assert_eq!(layer, vec![1, 0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_split_node() {
+        // Splitting 1 on the line 0 - 1 - 2 cuts the wire: 0 now ends
+        // in the output copy and the input copy starts a new wire to 2.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let (split, niset, noset, out_copy) =
+            split_node(&g, &nodeset([0]), &nodeset([2]), 1, &nodeset([0]));
+        assert_eq!(out_copy, 3);
+        assert_eq!(split, test_utils::graph(4, &[(1, 2), (0, 3)]));
+        assert_eq!(niset, nodeset([0, 1]));
+        assert_eq!(noset, nodeset([2, 3]));
+        // The flow of the split graph reflects the new I/O roles.
+        let (f, layer) = crate::flow::find(split, niset, noset).unwrap();
+        assert_eq!(f[&0], 3);
+        assert_eq!(f[&1], 2);
+        assert_eq!(layer, vec![1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_check_no_input_in_corrections() {
+        let f: std::collections::HashMap<_, _> =
+            [(0, nodeset([1])), (1, nodeset([2]))].into_iter().collect();
+        assert!(check_no_input_in_corrections(&f, &nodeset([0])).is_ok());
+        let err = check_no_input_in_corrections(&f, &nodeset([1])).unwrap_err();
+        assert!(err.to_string().contains("input node 1"));
+    }
+
+    #[test]
+    fn test_all_maximal_antichains() {
+        // Two disjoint chains 0 < 2 and 1 < 3: the maximal antichains
+        // pick one node from each chain.
+        let g = test_utils::graph(4, &[(0, 2), (1, 3)]);
+        let f: std::collections::HashMap<_, _> =
+            [(0, nodeset([2])), (1, nodeset([3]))].into_iter().collect();
+        let antichains = all_maximal_antichains(&g, &f, usize::MAX);
+        assert_eq!(antichains.len(), 4);
+        for expected in [
+            nodeset([0, 1]),
+            nodeset([0, 3]),
+            nodeset([2, 1]),
+            nodeset([2, 3]),
+        ] {
+            assert!(antichains.contains(&expected));
+        }
+        assert_eq!(all_maximal_antichains(&g, &f, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_correction_fan_in() {
+        // Overlapping correction sets: node 2 is targeted twice.
+        let f = [(0, nodeset([2])), (1, nodeset([2, 3]))].into_iter().collect();
+        let fan_in = correction_fan_in(&f);
+        assert_eq!(fan_in[&2], 2);
+        assert_eq!(fan_in[&3], 1);
+        assert!(!fan_in.contains_key(&0));
+    }
+
+    #[test]
+    fn test_merge_independent_layers() {
+        // The wire 0 - 1 - 2 and the pair 3 - 4, with 3 needlessly
+        // delayed to layer 2: layers 1 and 2 are independent and
+        // collapse, while 0 depends on 1 and stays above.
+        let g = test_utils::graph(5, &[(0, 1), (1, 2), (3, 4)]);
+        let f = [(0, nodeset([1])), (1, nodeset([2])), (3, nodeset([4]))]
+            .into_iter()
+            .collect();
+        let merged = merge_independent_layers(&g, &f, &vec![3, 1, 0, 2, 0]);
+        assert_eq!(merged, vec![2, 1, 0, 1, 0]);
+        // An already-tight layering is left alone.
+        assert_eq!(
+            merge_independent_layers(&g, &f, &merged),
+            vec![2, 1, 0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_critical_path_chain() {
+        // 0 - 1 - 2 with f(0) = {1}, f(1) = {2}: the whole chain.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let f = [(0, nodeset([1])), (1, nodeset([2]))].into_iter().collect();
+        assert_eq!(critical_path(&g, &f, &vec![2, 1, 0]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_critical_path_branching() {
+        // dag(0) = {1, 2}; the branch through 1 is longer and wins.
+        let g = test_utils::graph(4, &[(0, 1), (1, 2), (2, 3)]);
+        let f = [(0, nodeset([1])), (1, nodeset([2])), (2, nodeset([3]))]
+            .into_iter()
+            .collect();
+        assert_eq!(critical_path(&g, &f, &vec![3, 2, 1, 0]), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_cycle_rank() {
+        let tree = test_utils::graph(4, &[(0, 1), (1, 2), (1, 3)]);
+        assert_eq!(cycle_rank(&tree), 0);
+        let cycle = test_utils::graph(3, &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(cycle_rank(&cycle), 1);
+        // A cycle plus an isolated node: still one independent cycle.
+        let mixed = test_utils::graph(4, &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(cycle_rank(&mixed), 1);
+        assert_eq!(connected_components(&mixed), 2);
+    }
+
+    #[test]
+    fn test_identify_nodes() {
+        // Joining the two wires 0 - 1 and 2 - 3 at 1 = 2 gives a path.
+        let g = test_utils::graph(4, &[(0, 1), (2, 3)]);
+        let (merged, iset, oset, relabel) =
+            identify_nodes(&g, &nodeset([0]), &nodeset([3]), 1, 2);
+        assert_eq!(merged, test_utils::graph(3, &[(0, 1), (1, 2)]));
+        assert_eq!(iset, nodeset([0]));
+        assert_eq!(oset, nodeset([2]));
+        assert_eq!(relabel, vec![0, 1, 1, 2]);
+        assert!(crate::flow::find(merged, iset, oset).is_some());
+    }
+
+    #[test]
+    fn test_identify_nodes_drops_self_loop() {
+        // Merging adjacent nodes must not create a self-loop, and an
+        // input merged with an output belongs to both sets.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let (merged, iset, oset, _) = identify_nodes(&g, &nodeset([0]), &nodeset([1]), 0, 1);
+        assert_eq!(merged, test_utils::graph(2, &[(0, 1)]));
+        assert_eq!(iset, nodeset([0]));
+        assert_eq!(oset, nodeset([0]));
+        assert!(check_graph(&merged, &iset, &oset).is_ok());
+    }
+
+    #[test]
+    fn test_from_stabilizers_cluster() {
+        // Generators of the three-qubit cluster state.
+        let generators = vec![
+            (nodeset([0]), nodeset([1])),
+            (nodeset([1]), nodeset([0, 2])),
+            (nodeset([2]), nodeset([1])),
+        ];
+        let (g, cliffords) = from_stabilizers(&generators).unwrap();
+        assert_eq!(g, test_utils::graph(3, &[(0, 1), (1, 2)]));
+        assert_eq!(cliffords, vec![LocalClifford::I; 3]);
+    }
+
+    #[test]
+    fn test_from_stabilizers_product_state() {
+        // |00>: all-Z generators need a Hadamard on every qubit.
+        let generators = vec![(nodeset([]), nodeset([0])), (nodeset([]), nodeset([1]))];
+        let (g, cliffords) = from_stabilizers(&generators).unwrap();
+        assert_eq!(g, test_utils::graph(2, &[]));
+        assert_eq!(cliffords, vec![LocalClifford::H; 2]);
+    }
+
+    #[test]
+    fn test_from_stabilizers_not_graph_like() {
+        // Rank-deficient generators have no graph representative.
+        let generators = vec![(nodeset([0]), nodeset([])), (nodeset([0]), nodeset([]))];
+        assert!(from_stabilizers(&generators).is_none());
+    }
+
+    #[test]
+    fn test_complement() {
+        // The complement of a path on three nodes is the missing edge.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        assert_eq!(complement(&g), test_utils::graph(3, &[(0, 2)]));
+        // Complementing twice is the identity.
+        assert_eq!(complement(&complement(&g)), g);
+        assert!(check_graph(&complement(&g), &nodeset([]), &nodeset([])).is_ok());
+    }
+
+    #[test]
+    fn test_flow_to_csr() {
+        let f: std::collections::HashMap<usize, Nodes> =
+            [(0, nodeset([2, 1])), (1, nodeset([3]))].into_iter().collect();
+        let (offsets, targets) = flow_to_csr(&f, 4);
+        assert_eq!(offsets, vec![0, 2, 3, 3, 3]);
+        assert_eq!(targets, vec![1, 2, 3]);
+        // The CSR reconstructs every correction set.
+        for (&u, fu) in &f {
+            let row: Nodes = targets[offsets[u]..offsets[u + 1]].iter().copied().collect();
+            assert_eq!(&row, fu);
+        }
+    }
+
+    #[test]
+    fn test_layer_widths() {
+        assert_eq!(layer_widths(&vec![2, 0, 1, 0]), vec![2, 1, 1]);
+        assert_eq!(max_width(&vec![2, 0, 1, 0]), 2);
+        assert_eq!(layer_widths(&Vec::new()), Vec::<usize>::new());
+        assert_eq!(max_width(&Vec::new()), 0);
+    }
+
+    #[test]
+    fn test_layer_to_order() {
+        // Deepest layer first, increasing index within a layer, the
+        // outputs (layer 0) last.
+        let layer = vec![2, 0, 1, 0, 2];
+        let order = layer_to_order(&layer, &nodeset([1, 3]));
+        assert_eq!(order, vec![0, 4, 2, 1, 3]);
+        assert!(layer_to_order(&Vec::new(), &nodeset([])).is_empty());
+    }
+
+    #[test]
+    fn test_sorted_corrections() {
+        let f: std::collections::HashMap<usize, Nodes> =
+            [(1, nodeset([3])), (0, nodeset([2, 1]))].into_iter().collect();
+        let sorted = sorted_corrections(&f);
+        assert_eq!(
+            sorted.into_iter().collect::<Vec<_>>(),
+            vec![(0, vec![1, 2]), (1, vec![3])]
+        );
+    }
+
+    #[test]
+    fn test_depth_lower_bound() {
+        use crate::gflow::Plane;
+        // The bound never exceeds the depth actually found.
+        let cases: Vec<(Graph, Nodes, Nodes)> = vec![
+            (test_utils::graph(3, &[(0, 1), (1, 2)]), nodeset([0]), nodeset([2])),
+            (
+                test_utils::graph(4, &[(0, 1), (0, 2), (1, 3)]),
+                nodeset([]),
+                nodeset([1, 2]),
+            ),
+            (
+                test_utils::graph(4, &[(0, 2), (1, 3)]),
+                nodeset([0, 1]),
+                nodeset([2, 3]),
+            ),
+        ];
+        for (g, iset, oset) in cases {
+            let plane = test_utils::planes(
+                (0..g.len()).filter(|u| !oset.contains(u)).map(|u| (u, Plane::XY)),
+            );
+            let bound = depth_lower_bound(&g, &iset, &oset);
+            let (_, layer) = crate::gflow::find(g, iset, oset, plane).unwrap();
+            assert!(bound <= layer.iter().copied().max().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_delay_comparison() {
+        // A Pauli-X middle node collapses the chain to a single round.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = test_utils::pplanes([(0, crate::pflow::PPlane::XY), (1, crate::pflow::PPlane::X)]);
+        let (gdepth, pdepth, diff) =
+            delay_comparison(&g, &nodeset([0]), &nodeset([2]), &pplane).unwrap();
+        assert_eq!(gdepth, 2);
+        assert_eq!(pdepth, 1);
+        assert_eq!(diff, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_flow_signature() {
+        let f1 = [(0, nodeset([1])), (1, nodeset([2]))].into_iter().collect();
+        // Same structure with different members hashes equal.
+        let f2 = [(0, nodeset([2])), (1, nodeset([1]))].into_iter().collect();
+        let layer = vec![2, 1, 0];
+        assert_eq!(flow_signature(&f1, &layer), flow_signature(&f2, &layer));
+        // A different layering or correction-set profile differs.
+        let f3 = [(0, nodeset([1, 2])), (1, nodeset([2]))].into_iter().collect();
+        assert_ne!(flow_signature(&f1, &layer), flow_signature(&f3, &layer));
+        assert_ne!(
+            flow_signature(&f1, &layer),
+            flow_signature(&f1, &vec![1, 1, 0])
+        );
+    }
+
+    #[test]
+    fn test_estimate_cost_monotonic() {
+        let sparse = test_utils::graph(4, &[(0, 1), (1, 2), (2, 3)]);
+        let dense = test_utils::graph(4, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        let big = test_utils::graph(8, &(0..7).map(|i| (i, i + 1)).collect::<Vec<_>>());
+        let iset = nodeset([0]);
+        let oset = nodeset([3]);
+        assert!(estimate_cost(&dense, &iset, &oset) > estimate_cost(&sparse, &iset, &oset));
+        assert!(estimate_cost(&big, &iset, &nodeset([7])) > estimate_cost(&sparse, &iset, &oset));
+    }
+
+    #[test]
+    fn test_absolute_schedule() {
+        // Layers [2, 1, 0, 0] with outputs read at 10.0 and 12.5.
+        let layer = vec![2, 1, 0, 0];
+        let output_times = [(2, 10.0), (3, 12.5)].into_iter().collect();
+        let schedule = absolute_schedule(&layer, &output_times, 0.5);
+        assert_eq!(schedule[&0], 9.0);
+        assert_eq!(schedule[&1], 9.5);
+        assert_eq!(schedule[&2], 10.0);
+        assert_eq!(schedule[&3], 12.5);
+    }
+
+    #[test]
+    fn test_check_graph_ok() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        assert!(check_graph(&g, &nodeset([0]), &nodeset([2])).is_ok());
+    }
+
+    #[test]
+    fn test_validate_graph() {
+        assert!(validate_graph(&test_utils::graph(2, &[(0, 1)])).is_ok());
+        let mut g = test_utils::graph(2, &[]);
+        g[1].insert(0);
+        assert!(validate_graph(&g).is_err());
+    }
+
+    #[test]
+    fn test_check_graph_asymmetric() {
+        let mut g = test_utils::graph(2, &[]);
+        g[0].insert(1);
+        assert!(check_graph(&g, &nodeset([]), &nodeset([])).is_err());
+    }
+
+    #[test]
+    fn test_check_graph_selfloop() {
+        let mut g = test_utils::graph(1, &[]);
+        g[0].insert(0);
+        assert!(check_graph(&g, &nodeset([]), &nodeset([])).is_err());
+    }
+}
@@ -0,0 +1,555 @@
+//! Gaussian elimination over GF(2).
+
+use fixedbitset::FixedBitSet;
+
+/// Linear-equation solver over GF(2).
+///
+/// The working storage packs, per row, the coefficient matrix in the
+/// first `cols` bits followed by `neqs` right-hand side columns.
+/// The storage is moved into the solver with [`GF2Solver::attach`] and
+/// can be recovered with [`GF2Solver::detach`].
+pub struct GF2Solver {
+    work: Vec<FixedBitSet>,
+    rows: usize,
+    cols: usize,
+    neqs: usize,
+    /// Pivot column of each of the leading rows, populated lazily by
+    /// the first call to `eliminate`.
+    pivots: Option<Vec<usize>>,
+    /// Original index of the row currently stored at each position,
+    /// maintained across pivot swaps.
+    origin: Vec<usize>,
+    /// Original index of the row that witnessed the most recent
+    /// inconsistency, if any.
+    inconsistent: Option<usize>,
+    /// Number of `solve_in_place` calls made so far.
+    solves: u64,
+    /// Number of row additions performed by the elimination.
+    pivot_ops: u64,
+}
+
+impl GF2Solver {
+    /// Wraps `work` as a system with `neqs` right-hand sides.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `work` is empty, rows have inconsistent widths, or the
+    /// width is not larger than `neqs`.
+    pub fn attach(work: Vec<FixedBitSet>, neqs: usize) -> Self {
+        let rows = work.len();
+        assert!(rows > 0, "work buffer is empty");
+        let width = work[0].len();
+        assert!(work.iter().all(|row| row.len() == width), "row width mismatch");
+        assert!(width > neqs, "no coefficient columns");
+        Self {
+            work,
+            rows,
+            cols: width - neqs,
+            neqs,
+            pivots: None,
+            origin: (0..rows).collect(),
+            inconsistent: None,
+            solves: 0,
+            pivot_ops: 0,
+        }
+    }
+
+    /// Returns the number of [`GF2Solver::solve_in_place`] calls made
+    /// so far.
+    pub fn solve_count(&self) -> u64 {
+        self.solves
+    }
+
+    /// Returns the number of row additions performed by the
+    /// elimination, the dominant unit of work of the solver. A
+    /// machine-independent proxy for time spent.
+    pub fn pivot_ops(&self) -> u64 {
+        self.pivot_ops
+    }
+
+    /// Releases the working storage.
+    pub fn detach(self) -> Vec<FixedBitSet> {
+        self.work
+    }
+
+    /// Returns the original index of the row that witnessed the
+    /// inconsistency in the most recent failed [`GF2Solver::solve_in_place`].
+    ///
+    /// Row combinations during elimination are not tracked: the
+    /// reported row is the one occupying the zero-coefficient position
+    /// whose right-hand side remained set.
+    pub fn inconsistent_row(&self) -> Option<usize> {
+        self.inconsistent
+    }
+
+    /// Returns the rank of the coefficient block, eliminating first if
+    /// necessary.
+    pub fn rank(&mut self) -> usize {
+        if self.pivots.is_none() {
+            self.eliminate();
+        }
+        self.pivots.as_ref().expect("eliminated above").len()
+    }
+
+    /// Solves every attached equation, returning one solution per
+    /// right-hand side column.
+    ///
+    /// Inconsistent columns yield `None`; the others the solution with
+    /// all free variables zero, as in [`GF2Solver::solve_in_place`].
+    /// The single echelon reduction is amortized across all columns.
+    pub fn solve_all(&mut self) -> Vec<Option<FixedBitSet>> {
+        (0..self.neqs)
+            .map(|ieq| {
+                let mut out = FixedBitSet::with_capacity(self.cols);
+                self.solve_in_place(&mut out, ieq).then_some(out)
+            })
+            .collect()
+    }
+
+    /// Returns a basis of the null space of the coefficient block,
+    /// eliminating first if necessary.
+    ///
+    /// Each basis vector sets one free column to one, the other free
+    /// columns to zero, and the pivot columns as dictated by the
+    /// reduced form; together they span every homogeneous solution.
+    /// The basis is empty iff the block has full column rank. Adding
+    /// any combination of these vectors to a solution found by
+    /// [`GF2Solver::solve_in_place`] yields every other solution.
+    pub fn kernel_basis(&mut self) -> Vec<FixedBitSet> {
+        if self.pivots.is_none() {
+            self.eliminate();
+        }
+        let pivots = self.pivots.as_ref().expect("eliminated above");
+        let mut is_pivot = vec![false; self.cols];
+        for &c in pivots {
+            is_pivot[c] = true;
+        }
+        let mut basis = Vec::new();
+        for c in (0..self.cols).filter(|&c| !is_pivot[c]) {
+            let mut v = FixedBitSet::with_capacity(self.cols);
+            v.insert(c);
+            for (i, &p) in pivots.iter().enumerate() {
+                if self.work[i].contains(c) {
+                    v.insert(p);
+                }
+            }
+            basis.push(v);
+        }
+        basis
+    }
+
+    /// Brings the coefficient block into reduced row-echelon form,
+    /// applying the same operations to all right-hand sides.
+    ///
+    /// The pivot rule is fully deterministic: columns are scanned in
+    /// increasing index order and the pivot row of a column is the
+    /// lowest-index candidate row. The reduced form, and therefore the
+    /// solution picked by [`GF2Solver::solve_in_place`], depends only
+    /// on the attached matrix, never on platform hashing or iteration
+    /// order.
+    fn eliminate(&mut self) {
+        let mut pivots = Vec::new();
+        let mut r = 0;
+        for c in 0..self.cols {
+            if r >= self.rows {
+                break;
+            }
+            let Some(pr) = (r..self.rows).find(|&i| self.work[i].contains(c)) else {
+                continue;
+            };
+            self.work.swap(r, pr);
+            self.origin.swap(r, pr);
+            // Row addition is the elimination hotspot. `attach`
+            // guarantees uniform row widths, so each addition XORs the
+            // raw `usize` blocks pairwise; taking the pivot row out of
+            // the working storage first frees the inner loop of
+            // aliasing and width bookkeeping, leaving a branch-free
+            // form the compiler autovectorizes.
+            let prow = std::mem::take(&mut self.work[r]);
+            let mut additions = 0;
+            for (i, row) in self.work.iter_mut().enumerate() {
+                if i != r && row.contains(c) {
+                    for (a, &b) in row.as_mut_slice().iter_mut().zip(prow.as_slice()) {
+                        *a ^= b;
+                    }
+                    additions += 1;
+                }
+            }
+            self.work[r] = prow;
+            self.pivot_ops += additions;
+            pivots.push(c);
+            r += 1;
+        }
+        self.pivots = Some(pivots);
+    }
+
+    /// Solves the `ieq`-th equation, writing a solution into `out`.
+    ///
+    /// Returns `false` iff the equation is inconsistent. Free variables
+    /// are set to zero. On failure, [`GF2Solver::inconsistent_row`]
+    /// reports the witnessing constraint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ieq` is out of range or `out` cannot hold `cols` bits.
+    pub fn solve_in_place(&mut self, out: &mut FixedBitSet, ieq: usize) -> bool {
+        assert!(ieq < self.neqs, "equation index out of range");
+        assert!(out.len() >= self.cols, "output buffer too short");
+        self.solves += 1;
+        if self.pivots.is_none() {
+            self.eliminate();
+        }
+        let pivots = self.pivots.as_ref().expect("eliminated above");
+        let rhs = self.cols + ieq;
+        // Rows below the rank must have a zero right-hand side.
+        for i in pivots.len()..self.rows {
+            if self.work[i].contains(rhs) {
+                self.inconsistent = Some(self.origin[i]);
+                return false;
+            }
+        }
+        self.inconsistent = None;
+        out.clear();
+        for (i, &c) in pivots.iter().enumerate() {
+            if self.work[i].contains(rhs) {
+                out.insert(c);
+            }
+        }
+        true
+    }
+}
+
+/// Inverts an `n × n` matrix over GF(2).
+///
+/// `rows` are the bit-packed matrix rows, each at least `n` bits wide.
+/// Returns the rows of the inverse, or `None` for singular matrices.
+/// Internally runs one elimination of `[A | I]` through [`GF2Solver`],
+/// so it shares the bit-packed row operations of the solver.
+///
+/// # Panics
+///
+/// Panics if `rows.len() != n` or a row is narrower than `n` bits.
+pub fn invert(rows: &[FixedBitSet], n: usize) -> Option<Vec<FixedBitSet>> {
+    assert_eq!(rows.len(), n, "matrix is not square");
+    assert!(rows.iter().all(|row| row.len() >= n), "row narrower than n bits");
+    if n == 0 {
+        return Some(Vec::new());
+    }
+    let mut work = vec![FixedBitSet::with_capacity(2 * n); n];
+    for (r, row) in rows.iter().enumerate() {
+        for c in 0..n {
+            work[r].set(c, row.contains(c));
+        }
+        work[r].insert(n + r);
+    }
+    let mut solver = GF2Solver::attach(work, n);
+    if solver.rank() < n {
+        return None;
+    }
+    let mut out = FixedBitSet::with_capacity(n);
+    let mut inv = vec![FixedBitSet::with_capacity(n); n];
+    for c in 0..n {
+        // Column `c` of the inverse solves `A x = e_c`.
+        solver.solve_in_place(&mut out, c);
+        for r in out.ones() {
+            inv[r].insert(c);
+        }
+    }
+    Some(inv)
+}
+
+/// Density below which [`solve_adaptive`] switches to the sparse path.
+pub const SPARSE_DENSITY_THRESHOLD: f64 = 0.05;
+
+/// Solves `Ax = b` over GF(2), picking the elimination strategy by
+/// matrix density.
+///
+/// `rows` hold the sorted column indices of the nonzeros of each row.
+/// When the fill ratio is below [`SPARSE_DENSITY_THRESHOLD`] the
+/// elimination works directly on the index vectors; otherwise the rows
+/// are packed into bitsets and handed to [`GF2Solver`]. Both paths use
+/// the same pivot rule and return the same solution: free variables
+/// zero, or `None` when inconsistent.
+///
+/// # Panics
+///
+/// Panics if `rows` is empty, `rhs` has a different height, `cols` is
+/// zero, or an index is out of range or unsorted within its row.
+pub fn solve_adaptive(rows: &[Vec<usize>], rhs: &[bool], cols: usize) -> Option<FixedBitSet> {
+    assert!(!rows.is_empty(), "matrix is empty");
+    assert_eq!(rows.len(), rhs.len(), "matrix and rhs height mismatch");
+    assert!(cols > 0, "no coefficient columns");
+    assert!(
+        rows.iter()
+            .all(|row| row.windows(2).all(|w| w[0] < w[1]) && row.iter().all(|&c| c < cols)),
+        "row indices out of range or unsorted"
+    );
+    let nnz: usize = rows.iter().map(Vec::len).sum();
+    #[allow(clippy::cast_precision_loss)]
+    let density = nnz as f64 / (rows.len() * cols) as f64;
+    if density < SPARSE_DENSITY_THRESHOLD {
+        return solve_sparse(rows, rhs, cols);
+    }
+    let mut work = vec![FixedBitSet::with_capacity(cols + 1); rows.len()];
+    for (r, row) in rows.iter().enumerate() {
+        for &c in row {
+            work[r].insert(c);
+        }
+        work[r].set(cols, rhs[r]);
+    }
+    let mut solver = GF2Solver::attach(work, 1);
+    let mut out = FixedBitSet::with_capacity(cols);
+    solver.solve_in_place(&mut out, 0).then_some(out)
+}
+
+/// Sparse elimination on sorted index vectors, mirroring the dense
+/// pivot rule of [`GF2Solver`].
+fn solve_sparse(rows: &[Vec<usize>], rhs: &[bool], cols: usize) -> Option<FixedBitSet> {
+    let mut mat: Vec<Vec<usize>> = rows.to_vec();
+    let mut b: Vec<bool> = rhs.to_vec();
+    let nrows = mat.len();
+    let mut pivots = Vec::new();
+    let mut r = 0;
+    for c in 0..cols {
+        if r >= nrows {
+            break;
+        }
+        let Some(pr) = (r..nrows).find(|&i| mat[i].binary_search(&c).is_ok()) else {
+            continue;
+        };
+        mat.swap(r, pr);
+        b.swap(r, pr);
+        let prow = mat[r].clone();
+        let pb = b[r];
+        for i in 0..nrows {
+            if i != r && mat[i].binary_search(&c).is_ok() {
+                mat[i] = xor_sorted(&mat[i], &prow);
+                b[i] ^= pb;
+            }
+        }
+        pivots.push(c);
+        r += 1;
+    }
+    // Rows below the rank must have a zero right-hand side.
+    if b[pivots.len()..].iter().any(|&bit| bit) {
+        return None;
+    }
+    let mut out = FixedBitSet::with_capacity(cols);
+    for (i, &c) in pivots.iter().enumerate() {
+        if b[i] {
+            out.insert(c);
+        }
+    }
+    Some(out)
+}
+
+/// Symmetric difference of two sorted index vectors.
+fn xor_sorted(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => {
+                out.push(a[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                out.push(b[j]);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the working storage from 0/1 rows of `[matrix | rhs]`.
+    fn work_from(rows: &[&[u8]]) -> Vec<FixedBitSet> {
+        rows.iter()
+            .map(|row| {
+                let mut fbs = FixedBitSet::with_capacity(row.len());
+                for (c, &b) in row.iter().enumerate() {
+                    if b != 0 {
+                        fbs.insert(c);
+                    }
+                }
+                fbs
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_solve_unique() {
+        // x0 + x1 = 1, x1 = 1 => x0 = 0, x1 = 1.
+        let work = work_from(&[&[1, 1, 1], &[0, 1, 1]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        let mut out = FixedBitSet::with_capacity(2);
+        assert!(solver.solve_in_place(&mut out, 0));
+        assert!(!out.contains(0));
+        assert!(out.contains(1));
+    }
+
+    #[test]
+    fn test_solve_inconsistent() {
+        // x0 = 1 and x0 = 0.
+        let work = work_from(&[&[1, 1], &[1, 0]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        let mut out = FixedBitSet::with_capacity(1);
+        assert!(!solver.solve_in_place(&mut out, 0));
+    }
+
+    #[test]
+    fn test_inconsistent_row_reported() {
+        // x1 = 1, x0 + x1 = 0 and x0 = 0 conflict; the pivot swap moves
+        // the third constraint into the witnessing position.
+        let work = work_from(&[&[0, 1, 1], &[1, 1, 0], &[1, 0, 0]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        let mut out = FixedBitSet::with_capacity(2);
+        assert!(solver.inconsistent_row().is_none());
+        assert!(!solver.solve_in_place(&mut out, 0));
+        assert_eq!(solver.inconsistent_row(), Some(2));
+    }
+
+    #[test]
+    fn test_inconsistent_row_cleared() {
+        let work = work_from(&[&[1, 0, 1, 1], &[1, 0, 0, 1]]);
+        let mut solver = GF2Solver::attach(work, 2);
+        let mut out = FixedBitSet::with_capacity(2);
+        assert!(!solver.solve_in_place(&mut out, 0));
+        assert_eq!(solver.inconsistent_row(), Some(1));
+        assert!(solver.solve_in_place(&mut out, 1));
+        assert!(solver.inconsistent_row().is_none());
+    }
+
+    #[test]
+    fn test_solve_underdetermined() {
+        // x0 + x1 = 1: free variable is fixed to zero.
+        let work = work_from(&[&[1, 1, 1]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        let mut out = FixedBitSet::with_capacity(2);
+        assert!(solver.solve_in_place(&mut out, 0));
+        assert!(out.contains(0));
+        assert!(!out.contains(1));
+    }
+
+    #[test]
+    fn test_kernel_basis() {
+        // x0 + x2 = 0, x1 + x2 = 0: the kernel is spanned by (1, 1, 1).
+        let work = work_from(&[&[1, 0, 1, 0], &[0, 1, 1, 0]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        let basis = solver.kernel_basis();
+        assert_eq!(basis.len(), 1);
+        assert!(basis[0].contains(0) && basis[0].contains(1) && basis[0].contains(2));
+    }
+
+    #[test]
+    fn test_kernel_basis_full_rank() {
+        let work = work_from(&[&[1, 0, 1], &[0, 1, 0]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        assert!(solver.kernel_basis().is_empty());
+    }
+
+    #[test]
+    fn test_rank() {
+        // The third row is the sum of the first two.
+        let work = work_from(&[&[1, 0, 1, 0], &[0, 1, 1, 0], &[1, 1, 0, 0]]);
+        let mut solver = GF2Solver::attach(work, 1);
+        assert_eq!(solver.rank(), 2);
+    }
+
+    #[test]
+    fn test_deterministic_reduction() {
+        // The pivot rule depends only on the matrix, so the reduced
+        // working storage is bit-for-bit reproducible.
+        let rows: &[&[u8]] = &[&[0, 1, 1, 0, 1], &[1, 1, 0, 1, 0], &[1, 0, 1, 1, 1]];
+        let reduce = || {
+            let mut solver = GF2Solver::attach(work_from(rows), 1);
+            let mut out = FixedBitSet::with_capacity(4);
+            solver.solve_in_place(&mut out, 0);
+            (solver.detach(), out)
+        };
+        let (work1, out1) = reduce();
+        let (work2, out2) = reduce();
+        assert_eq!(work1, work2);
+        assert_eq!(out1, out2);
+        // Pivots are the lowest-index rows and columns: the second row
+        // is swapped up for column zero and the rank-two remainder
+        // cancels to a zero row.
+        assert_eq!(
+            work1,
+            work_from(&[&[1, 0, 1, 1, 1], &[0, 1, 1, 0, 1], &[0, 0, 0, 0, 0]])
+        );
+    }
+
+    #[test]
+    fn test_solve_all() {
+        // First column is consistent, second hits the zero row.
+        let work = work_from(&[&[1, 0, 1, 1], &[0, 0, 0, 1]]);
+        let mut solver = GF2Solver::attach(work, 2);
+        let solutions = solver.solve_all();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions[0].as_ref().unwrap().contains(0));
+        assert!(solutions[1].is_none());
+    }
+
+    #[test]
+    fn test_invert() {
+        // The upper-triangular [[1, 1], [0, 1]] is its own inverse.
+        let rows = work_from(&[&[1, 1], &[0, 1]]);
+        let inv = invert(&rows, 2).unwrap();
+        assert_eq!(inv, rows);
+    }
+
+    #[test]
+    fn test_invert_singular() {
+        let rows = work_from(&[&[1, 1], &[1, 1]]);
+        assert!(invert(&rows, 2).is_none());
+    }
+
+    #[test]
+    fn test_solve_adaptive_sparse() {
+        // 2 nonzeros over 2 x 64 forces the sparse path.
+        let rows = vec![vec![0], vec![63]];
+        let rhs = vec![true, true];
+        let out = solve_adaptive(&rows, &rhs, 64).unwrap();
+        assert!(out.contains(0) && out.contains(63));
+        assert_eq!(out.count_ones(..), 2);
+    }
+
+    #[test]
+    fn test_solve_adaptive_matches_dense() {
+        // Dense enough for the bitset path; both paths agree anyway.
+        let rows = vec![vec![0, 1], vec![1]];
+        let rhs = vec![true, true];
+        let dense = solve_adaptive(&rows, &rhs, 2).unwrap();
+        let sparse = solve_sparse(&rows, &rhs, 2).unwrap();
+        assert_eq!(dense, sparse);
+        assert!(!dense.contains(0) && dense.contains(1));
+    }
+
+    #[test]
+    fn test_solve_adaptive_inconsistent() {
+        let rows = vec![vec![0], vec![0]];
+        assert!(solve_adaptive(&rows, &[true, false], 2).is_none());
+    }
+
+    #[test]
+    fn test_solve_multiple_rhs() {
+        let work = work_from(&[&[1, 0, 1, 0], &[0, 1, 1, 1]]);
+        let mut solver = GF2Solver::attach(work, 2);
+        let mut out = FixedBitSet::with_capacity(2);
+        assert!(solver.solve_in_place(&mut out, 0));
+        assert!(out.contains(0) && out.contains(1));
+        assert!(solver.solve_in_place(&mut out, 1));
+        assert!(!out.contains(0) && out.contains(1));
+    }
+}
@@ -0,0 +1,1731 @@
+//! Maximally-delayed generalized flow (gflow).
+
+use std::collections::HashMap;
+
+use fixedbitset::FixedBitSet;
+
+use anyhow::ensure;
+use rayon::prelude::*;
+
+use crate::common::{
+    check_domain, check_graph, check_initial, check_no_input_in_corrections, odd_neighbors,
+    Graph, Layer, Nodes,
+};
+use crate::gf2_linalg::GF2Solver;
+
+/// Measurement plane of a non-Pauli measurement.
+///
+/// Exported to Python as an enum; the variant values coincide with the
+/// wire-format integers, so `Plane.XY == 0` holds.
+#[pyo3::pyclass(eq, eq_int)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Plane {
+    XY,
+    YZ,
+    XZ,
+}
+
+/// Correction function of a gflow: maps each measured node to its
+/// correction set.
+pub type GFlow = HashMap<usize, Nodes>;
+
+/// Finds a maximally-delayed gflow, if one exists.
+///
+/// `plane` must assign a measurement plane to each non-output node.
+/// Returns the correction function together with the layer of each
+/// node, outputs being layer `0`. The result is deterministic: the
+/// solver's column basis is in increasing node order and the returned
+/// correction set is the unique solution with all free variables zero.
+///
+/// A gflow requires `|iset| <= |oset|`; when there are more inputs
+/// than outputs the search returns `None` immediately instead of
+/// grinding through rounds that cannot succeed.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer)> {
+    find_with_fixed(g, iset, oset, plane, &GFlow::new())
+}
+
+/// Finds a maximally-delayed focused gflow, if a gflow exists.
+///
+/// Starting from the result of [`find`], each correction set is
+/// focused in increasing layer order: as long as its odd neighborhood
+/// contains an XY-measured node other than the corrected one, that
+/// node's (already focused) correction set is folded in by symmetric
+/// difference. Afterwards the odd neighborhood of every correction set
+/// meets the measured XY nodes only in the corrected node itself; the
+/// layering is unchanged and remains valid.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_focused(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer)> {
+    let (mut f, layer) = find(g.clone(), iset, oset.clone(), plane.clone())?;
+    // Focusing queries odd neighborhoods of one graph over and over;
+    // the cached bitset rows make each query a handful of XORs.
+    let bits = crate::common::AdjacencyBits::new(&g);
+    let mut order: Vec<usize> = f.keys().copied().collect();
+    // Smaller layers are measured later; their sets are focused first
+    // so each fold below is final.
+    order.sort_unstable_by_key(|&u| layer[u]);
+    for u in order {
+        loop {
+            let odd = bits.odd_neighbors(&f[&u]);
+            let unfocused = odd
+                .iter()
+                .filter(|&&w| w != u && !oset.contains(&w) && plane[&w] == Plane::XY)
+                .min()
+                .copied();
+            let Some(w) = unfocused else {
+                break;
+            };
+            let fw = f[&w].clone();
+            let fu = f.get_mut(&u).expect("measured above");
+            for v in fw {
+                if !fu.remove(&v) {
+                    fu.insert(v);
+                }
+            }
+        }
+    }
+    Some((f, layer))
+}
+
+/// Finds a maximally-extended gflow, if a gflow exists.
+///
+/// The correction function is the one [`find`] returns; only the
+/// schedule differs. Where the maximally-delayed layering measures
+/// every node as late as possible, this one pushes each node into the
+/// largest layer consistent with the flow and its depth, so it is
+/// measured as early as possible: no node could move to an earlier
+/// round without breaking the ordering of some correction set or odd
+/// neighborhood. Comparing the two layerings shows the scheduling
+/// slack of each node.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_extended(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer) = find(g.clone(), iset, oset.clone(), plane)?;
+    let depth = layer.iter().copied().max().unwrap_or(0);
+    // Each measured node starts at the top and is pulled down by the
+    // nodes measured before it: `u` bounds everything in its correction
+    // set and odd neighborhood to strictly lower layers. Decreasing
+    // delayed layer is a topological order of these constraints, so one
+    // sweep finalizes every node before it bounds its successors.
+    let mut order: Vec<usize> = f.keys().copied().collect();
+    order.sort_unstable_by_key(|&u| std::cmp::Reverse(layer[u]));
+    let mut extended = vec![0; layer.len()];
+    for &u in f.keys() {
+        extended[u] = depth;
+    }
+    for u in order {
+        let fu = &f[&u];
+        let mut below = odd_neighbors(&g, fu);
+        below.extend(fu.iter().copied());
+        for w in below {
+            if w != u && !oset.contains(&w) {
+                extended[w] = extended[w].min(extended[u] - 1);
+            }
+        }
+    }
+    Some((f, extended))
+}
+
+/// Extends a gflow after appending one node to the graph.
+///
+/// `g`, `iset`, `oset` and `plane` describe the extended graph: the
+/// appended node is `g.len() - 1` and `(f, layer)` must be a valid
+/// gflow of the graph without it, which is trusted rather than
+/// re-checked. Two cheap cases skip the full search: an appended
+/// output extends any gflow verbatim, and an appended XY node
+/// corrected by a single output neighbor with no other measured
+/// contact joins layer `1`, provided every existing correction set it
+/// now oddly intersects sits in layer `2` or deeper. Anything else
+/// falls back to [`find`] on the whole graph. The result is a valid
+/// gflow but not necessarily maximally delayed; rerun [`find`] when
+/// the delay structure matters.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if `layer` does
+/// not cover exactly the nodes before the appended one.
+pub fn extend_with_node(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    f: &GFlow,
+    layer: &Layer,
+) -> Option<(GFlow, Layer)> {
+    check_graph(&g, &iset, &oset).expect("graph is malformed");
+    let n = g.len();
+    assert_eq!(
+        layer.len() + 1,
+        n,
+        "layer must cover all but the appended node"
+    );
+    let u = n - 1;
+    // An appended output never constrains anyone: every odd
+    // neighborhood it joins only has to dominate layer 0.
+    if oset.contains(&u) {
+        let mut layer = layer.clone();
+        layer.push(0);
+        return Some((f.clone(), layer));
+    }
+    // An appended XY node can join layer 1 when some output neighbor
+    // corrects it without touching measured nodes, and every existing
+    // correction set now reaching it oddly is measured strictly
+    // earlier than layer 1.
+    if plane.get(&u) == Some(&Plane::XY) {
+        let corrector = g[u]
+            .iter()
+            .filter(|&&o| {
+                oset.contains(&o)
+                    && !iset.contains(&o)
+                    && g[o].iter().all(|&w| w == u || oset.contains(&w))
+            })
+            .min()
+            .copied();
+        let undisturbed = |(&w, fw): (&usize, &Nodes)| {
+            fw.iter().filter(|v| g[u].contains(v)).count() % 2 == 0 || layer[w] > 1
+        };
+        if let Some(o) = corrector
+            && f.iter().all(undisturbed)
+        {
+            let mut f = f.clone();
+            f.insert(u, Nodes::from([o]));
+            let mut layer = layer.clone();
+            layer.push(1);
+            return Some((f, layer));
+        }
+    }
+    find(g, iset, oset, plane)
+}
+
+/// Finds a maximally-delayed gflow as a bit-packed correction matrix.
+///
+/// Row `u` of the matrix has bit `v` set iff `v` is in `f(u)`;
+/// outputs keep an empty row. Linear-algebra consumers get the GF(2)
+/// matrix directly instead of rebuilding it from the hash-map
+/// representation.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_as_matrix(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(Vec<FixedBitSet>, Layer)> {
+    let (f, layer) = find(g, iset, oset, plane)?;
+    let n = layer.len();
+    let mut mat = vec![FixedBitSet::with_capacity(n); n];
+    for (&u, fu) in &f {
+        for &v in fu {
+            mat[u].insert(v);
+        }
+    }
+    Some((mat, layer))
+}
+
+/// Finds maximally-delayed gflows for a batch of inputs in parallel.
+///
+/// Entries run independently on the rayon pool; results come back in
+/// input order. Amortizes per-call overhead when sweeping many small
+/// graphs.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails for any entry.
+#[allow(clippy::type_complexity)]
+pub fn find_batch(
+    inputs: Vec<(Graph, Nodes, Nodes, HashMap<usize, Plane>)>,
+) -> Vec<Option<(GFlow, Layer)>> {
+    inputs
+        .into_par_iter()
+        .map(|(g, iset, oset, plane)| find(g, iset, oset, plane))
+        .collect()
+}
+
+/// Finds the depth of the maximally-delayed gflow, if one exists.
+///
+/// The depth is the number of the deepest layer, i.e. `0` when every
+/// node is an output.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_depth(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<usize> {
+    let (_, layer) = find(g, iset, oset, plane)?;
+    Some(layer.iter().copied().max().unwrap_or(0))
+}
+
+/// Finds a maximally-delayed gflow of depth at most `max_depth`.
+///
+/// Equivalent to [`find`], except the search gives up and returns
+/// `None` as soon as a round beyond the bound would be needed, instead
+/// of computing the deeper layers only to have the caller reject them.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_max_depth(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    max_depth: usize,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        Some(max_depth),
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Finds a maximally-delayed gflow with smallest correction sets.
+///
+/// Within each round every solution for a node differs from the
+/// finder's by a combination of kernel vectors; all combinations are
+/// scanned and the lowest Hamming weight wins, ties going to the
+/// earlier combination. The layering, and with it the depth, is the
+/// same as [`find`]'s. The scan is exponential in a round's number of
+/// free variables and meant for small graphs.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if a round has
+/// more free variables than bits in a machine word.
+pub fn find_min_weight(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        true,
+        None,
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Finds a maximally-delayed gflow honoring caller-fixed corrections.
+///
+/// Nodes in `fixed` never enter the solver: each is corrected with the
+/// given set in the first round where it becomes admissible. Returns
+/// `None` if some fixed set never does.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if `fixed`
+/// covers an unmeasured node.
+pub fn find_with_fixed(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    fixed: &GFlow,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        fixed,
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        None,
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Finds a gflow with some nodes forced into caller-specified layers.
+///
+/// Nodes in `fixed_layers` are only corrected in exactly the given
+/// layer: earlier rounds skip them and a node missing its layer kills
+/// the search, as do pins placing a measured node in layer `0` or an
+/// output elsewhere. Rounds may stay empty while every solvable node
+/// is pinned further out, so sparse pins like `{u: 5}` work without
+/// padding the layers in between. Unpinned nodes keep their usual
+/// maximally-delayed layers, subject to the extra ordering constraints
+/// the pins induce.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_fixed_layers(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    fixed_layers: &HashMap<usize, usize>,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        fixed_layers,
+        false,
+        None,
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Checks a candidate gflow against the definition.
+///
+/// Validates the graph and domain invariants, the layering, and the
+/// gflow conditions: the plane condition on each correction set and
+/// the strict ordering of correction sets and odd neighborhoods after
+/// their node. Errors name the first offending node and the violated
+/// condition. Intended for gflows computed elsewhere; the finders' own
+/// results always pass.
+pub fn verify(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    plane: &HashMap<usize, Plane>,
+    f: &GFlow,
+    layer: &Layer,
+) -> anyhow::Result<()> {
+    check_graph(g, iset, oset)?;
+    let n = g.len();
+    let vset: Nodes = (0..n).collect();
+    check_domain(plane, &vset, oset)?;
+    check_domain(f, &vset, oset)?;
+    ensure!(layer.len() == n, "layer length mismatch");
+    check_initial(layer, oset)?;
+    check_no_input_in_corrections(f, iset)?;
+    for (&u, fu) in f {
+        ensure!(layer[u] > 0, "measured node in layer 0: {u}");
+        let odd = odd_neighbors(g, fu);
+        let ok = match plane[&u] {
+            Plane::XY => !fu.contains(&u) && odd.contains(&u),
+            Plane::XZ => fu.contains(&u) && odd.contains(&u),
+            Plane::YZ => fu.contains(&u) && !odd.contains(&u),
+        };
+        ensure!(ok, "plane condition violated: {u}");
+        for &w in fu {
+            ensure!(
+                w == u || layer[w] < layer[u],
+                "correction set of {u} not ordered after it: {w}"
+            );
+        }
+        for &w in &odd {
+            ensure!(
+                w == u || layer[w] < layer[u],
+                "odd neighborhood of {u} not ordered after it: {w}"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Raw solver output of a gflow search.
+///
+/// `tab[k - 1]` is the column basis of round `k`: the candidate
+/// correctors, in column order. `x[&u]` is the solution bitvector of
+/// `u` over the basis of its round, exactly as produced by the solver;
+/// decoding it through the basis, plus `u` itself for the YZ and XZ
+/// planes, reproduces the correction set. Caller-fixed nodes bypass
+/// the solver and have no vector.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct RawSolutions {
+    /// Column basis of each round.
+    pub tab: Vec<Vec<usize>>,
+    /// Coefficient rank of each round's system.
+    pub rank: Vec<usize>,
+    /// Per-node raw solution bitvector.
+    pub x: HashMap<usize, FixedBitSet>,
+    /// Totals of the GF(2) work performed across all rounds.
+    pub stats: SolveStats,
+}
+
+/// Totals of the GF(2) work performed by a gflow search.
+///
+/// A machine-independent proxy for the work done, more stable than
+/// wall-clock time when studying how the search scales; collected by
+/// [`find_with_solve_stats`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SolveStats {
+    /// Number of solver invocations, one per node-and-round attempt.
+    pub solves: u64,
+    /// Number of GF(2) row additions performed by the eliminations.
+    pub pivot_ops: u64,
+}
+
+/// Finds a maximally-delayed gflow, also returning the solver's raw
+/// output for callers that recombine corrections algebraically.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_raw(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer, RawSolutions)> {
+    find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        None,
+    )
+    .ok()
+}
+
+/// Finds a maximally-delayed gflow, totalling the GF(2) work the
+/// search performed.
+///
+/// The flow and layering are those of [`find`]; the stats count every
+/// solver invocation and row addition across the rounds, for scaling
+/// studies that want a workload measure instead of wall-clock time.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_solve_stats(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer, SolveStats)> {
+    let (f, layer, raw) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        None,
+    )
+    .ok()?;
+    Some((f, layer, raw.stats))
+}
+
+/// Why a gflow search came back empty; see [`find_with_reason`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FindFailure {
+    /// The graph has no nodes; there is nothing to search.
+    EmptyGraph,
+    /// Some node can never be corrected: no gflow exists. Carries the
+    /// stuck frontier, pinpointing where the circuit design breaks
+    /// down.
+    NoFlow {
+        /// Nodes still uncorrected when progress stopped.
+        blocked: Nodes,
+        /// Round the search was working on when it gave up.
+        layer: usize,
+    },
+    /// More inputs than outputs: no gflow can exist, whatever the
+    /// graph looks like.
+    TooManyInputs,
+    /// A round past the requested depth bound would be needed.
+    DepthExceeded,
+}
+
+/// Finds a maximally-delayed gflow, saying why when there is none.
+///
+/// Behaves like [`find`] (like [`find_with_max_depth`] when a bound is
+/// given) but distinguishes the degenerate empty graph, a genuinely
+/// flowless graph, an input set outnumbering the outputs, and a depth
+/// bound cutting the search short, so a failure can be diagnosed
+/// without reading the trace. A flowless graph additionally names the
+/// stuck frontier: the nodes still unsolved in the round where
+/// progress stopped.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_reason(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    max_depth: Option<usize>,
+) -> Result<(GFlow, Layer), FindFailure> {
+    if g.is_empty() {
+        return Err(FindFailure::EmptyGraph);
+    }
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        max_depth,
+    )?;
+    Ok((f, layer))
+}
+
+/// Counts the valid correction sets of each measured node at its
+/// layer.
+///
+/// The solutions of a node's round form an affine space of dimension
+/// `columns - rank`, so the count is `2^(free variables)` without any
+/// enumeration, saturating at `u64::MAX`. Returns `None` if no gflow
+/// exists.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn count_solutions(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<HashMap<usize, u64>> {
+    let (f, layer, raw) = find_raw(g, iset, oset, plane)?;
+    Some(
+        f.keys()
+            .map(|&u| {
+                let free = raw.tab[layer[u] - 1].len() - raw.rank[layer[u] - 1];
+                let count = u32::try_from(free)
+                    .ok()
+                    .and_then(|s| 1u64.checked_shl(s))
+                    .unwrap_or(u64::MAX);
+                (u, count)
+            })
+            .collect(),
+    )
+}
+
+/// Enumerates the valid correction sets of a single measured node.
+///
+/// Rebuilds the GF(2) system of `u`'s round from the layering and
+/// combines one particular solution with every subset of the kernel
+/// basis, covering the whole affine solution space. At most `cap` sets
+/// are returned, guarding against exponential nullities; the finder's
+/// own solution comes first. Returns `None` if no gflow exists, so
+/// callers can pick corrections by a secondary cost such as set size.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if `u` is
+/// unmeasured.
+pub fn enumerate_solutions(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    u: usize,
+    cap: usize,
+) -> Option<Vec<Nodes>> {
+    assert!(plane.contains_key(&u), "node is unmeasured: {u}");
+    let (_, layer) = find(g.clone(), iset.clone(), oset, plane.clone())?;
+    let k = layer[u];
+    let n = g.len();
+    // Round `k` seen through the layering: columns are the non-inputs
+    // already processed, rows the nodes still uncorrected.
+    let colset: Vec<usize> = (0..n)
+        .filter(|v| layer[*v] < k && !iset.contains(v))
+        .collect();
+    let mut rowset: Vec<usize> = plane.keys().copied().filter(|&w| layer[w] >= k).collect();
+    rowset.sort_unstable();
+    let width = colset.len() + 1;
+    let mut work = vec![FixedBitSet::with_capacity(width); rowset.len()];
+    for (r, &w) in rowset.iter().enumerate() {
+        for (c, &v) in colset.iter().enumerate() {
+            if g[w].contains(&v) {
+                work[r].insert(c);
+            }
+        }
+        let mut bit = match plane[&u] {
+            Plane::XY | Plane::XZ => w == u,
+            Plane::YZ => false,
+        };
+        if plane[&u] != Plane::XY && g[w].contains(&u) {
+            bit = !bit;
+        }
+        work[r].set(colset.len(), bit);
+    }
+    let mut solver = GF2Solver::attach(work, 1);
+    let mut out = FixedBitSet::with_capacity(colset.len());
+    // The finder solved this very system when it corrected `u`.
+    assert!(
+        solver.solve_in_place(&mut out, 0),
+        "round system must be solvable"
+    );
+    let kernel = solver.kernel_basis();
+    let total = u32::try_from(kernel.len())
+        .ok()
+        .and_then(|d| 1usize.checked_shl(d))
+        .unwrap_or(usize::MAX);
+    let mut sols = Vec::new();
+    for mask in 0..total.min(cap) {
+        let mut x = out.clone();
+        for (i, v) in kernel.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                x ^= v;
+            }
+        }
+        let mut fu: Nodes = x.ones().map(|c| colset[c]).collect();
+        if plane[&u] != Plane::XY {
+            fu.insert(u);
+        }
+        sols.push(fu);
+    }
+    Some(sols)
+}
+
+/// Outcome of [`find_unique`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Uniqueness {
+    /// Every correction set is the only solution of its round.
+    Unique(GFlow, Layer),
+    /// The listed nodes admit alternative correction sets.
+    Ambiguous(Nodes),
+}
+
+/// Finds a maximally-delayed gflow, accepting it only when unique.
+///
+/// A node's solution space has dimension `columns - rank` in its
+/// round; the flow is unique iff that nullity is zero for every
+/// measured node. Returns `None` if no gflow exists at all.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_unique(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<Uniqueness> {
+    let (f, layer, raw) = find_raw(g, iset, oset, plane)?;
+    let slack: Nodes = f
+        .keys()
+        .filter(|&&u| raw.tab[layer[u] - 1].len() > raw.rank[layer[u] - 1])
+        .copied()
+        .collect();
+    Some(if slack.is_empty() {
+        Uniqueness::Unique(f, layer)
+    } else {
+        Uniqueness::Ambiguous(slack)
+    })
+}
+
+/// Finds a maximally-delayed gflow with some nodes measured last.
+///
+/// Pinned nodes are corrected in the very first round, i.e. end up in
+/// the lowest measured layer, and never appear in another node's
+/// correction set. Fails if some pinned node cannot be corrected using
+/// outputs alone.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if a pinned
+/// node is unmeasured.
+pub fn find_with_pinned(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    pinned: &Nodes,
+) -> Option<(GFlow, Layer)> {
+    assert!(
+        pinned.iter().all(|u| plane.contains_key(u)),
+        "pinned node is unmeasured"
+    );
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        false,
+        pinned,
+        &HashMap::new(),
+        false,
+        None,
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Finds a gflow whose corrections only reach into the next layer.
+///
+/// Correction sets may only use nodes corrected in the immediately
+/// preceding round (the node itself aside, for the YZ and XZ planes),
+/// modeling hardware that can only target the very next measurement
+/// round. Fails if no such flow exists even when an unrestricted gflow
+/// does.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_next_layer(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> Option<(GFlow, Layer)> {
+    let (f, layer, _) = find_core(
+        g,
+        iset,
+        oset,
+        plane,
+        &GFlow::new(),
+        true,
+        &Nodes::new(),
+        &HashMap::new(),
+        false,
+        None,
+    )
+    .ok()?;
+    Some((f, layer))
+}
+
+/// Shared search loop of the gflow finders.
+#[allow(clippy::too_many_arguments)]
+fn find_core(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+    fixed: &GFlow,
+    adjacent_only: bool,
+    pinned: &Nodes,
+    fixed_layers: &HashMap<usize, usize>,
+    minimize: bool,
+    max_depth: Option<usize>,
+) -> Result<(GFlow, Layer, RawSolutions), FindFailure> {
+    check_graph(&g, &iset, &oset).expect("graph is malformed");
+    assert!(
+        fixed.keys().all(|u| plane.contains_key(u)),
+        "fixed correction for an unmeasured node"
+    );
+    let n = g.len();
+    let vset: Nodes = (0..n).collect();
+    check_domain(&plane, &vset, &oset).expect("plane domain is malformed");
+    let mut f = GFlow::new();
+    let mut layer = vec![0; n];
+    let mut raw = RawSolutions::default();
+    check_initial(&layer, &oset).expect("initial layer is malformed");
+    // Nodes not yet corrected.
+    let mut ocset: Nodes = vset.difference(&oset).copied().collect();
+    // Every node is an output (or the graph is empty): the empty flow
+    // of depth 0 stands without entering the round machinery.
+    if ocset.is_empty() {
+        return Ok((f, layer, raw));
+    }
+    // More inputs than outputs: no gflow exists (the flow conditions
+    // force an injection of the non-outputs into the non-inputs), so
+    // reject up front instead of stalling after some rounds.
+    if iset.len() > oset.len() {
+        log::debug!(
+            "gflow: {} inputs exceed {} outputs",
+            iset.len(),
+            oset.len()
+        );
+        return Err(FindFailure::TooManyInputs);
+    }
+    // An isolated measured node can never satisfy `u ∈ Odd(f(u))`,
+    // which every plane but YZ requires, so the search is doomed before
+    // the first round; name the nodes instead of stalling later.
+    {
+        let blocked: Nodes = ocset
+            .iter()
+            .filter(|&&u| g[u].is_empty() && plane[&u] != Plane::YZ)
+            .copied()
+            .collect();
+        if !blocked.is_empty() {
+            log::debug!("gflow: isolated measured nodes cannot be corrected: {blocked:?}");
+            return Err(FindFailure::NoFlow { blocked, layer: 0 });
+        }
+    }
+    // A measured node cannot sit in layer 0 and an output cannot leave
+    // it, so such pins are unsatisfiable from the start.
+    {
+        let blocked: Nodes = fixed_layers
+            .iter()
+            .filter(|&(u, &l)| (l == 0) != oset.contains(u))
+            .map(|(&u, _)| u)
+            .collect();
+        if !blocked.is_empty() {
+            return Err(FindFailure::NoFlow { blocked, layer: 0 });
+        }
+    }
+    // Nodes corrected in the previous round, for `adjacent_only`.
+    let mut prev = oset.clone();
+    for k in 1.. {
+        if ocset.is_empty() {
+            break;
+        }
+        // Nodes remain past the depth cap: no flow of that depth.
+        if max_depth.is_some_and(|d| k > d) {
+            return Err(FindFailure::DepthExceeded);
+        }
+        // Columns: processed non-inputs, restricted to the previous
+        // round when requested. Rows and right-hand sides: unprocessed
+        // nodes. Both are scanned in increasing node order so the work
+        // buffer, and with it the solution, never depends on hash-set
+        // iteration order.
+        let colset: Vec<usize> = (0..n)
+            .filter(|u| {
+                !ocset.contains(u)
+                    && !iset.contains(u)
+                    && !pinned.contains(u)
+                    && (!adjacent_only || prev.contains(u))
+            })
+            .collect();
+        let mut rowset: Vec<usize> = ocset.iter().copied().collect();
+        rowset.sort_unstable();
+        if colset.is_empty() {
+            return Err(FindFailure::NoFlow {
+                blocked: ocset,
+                layer: k,
+            });
+        }
+        raw.tab.push(colset.clone());
+        log::debug!(
+            "gflow round {k}: {} columns, {} unsolved",
+            colset.len(),
+            rowset.len()
+        );
+        let width = colset.len() + rowset.len();
+        let mut work = vec![FixedBitSet::with_capacity(width); rowset.len()];
+        for (r, &w) in rowset.iter().enumerate() {
+            for (c, &v) in colset.iter().enumerate() {
+                if g[w].contains(&v) {
+                    work[r].insert(c);
+                }
+            }
+        }
+        for (ieq, &u) in rowset.iter().enumerate() {
+            let rhs = colset.len() + ieq;
+            for (r, &w) in rowset.iter().enumerate() {
+                // Conditions on Odd(f(u)): `u` itself per plane, other
+                // unprocessed nodes must stay out. For YZ and XZ the
+                // correction set contains `u`, which is folded into the
+                // right-hand side.
+                let mut bit = match plane[&u] {
+                    Plane::XY | Plane::XZ => w == u,
+                    Plane::YZ => false,
+                };
+                if plane[&u] != Plane::XY && g[w].contains(&u) {
+                    bit = !bit;
+                }
+                work[r].set(rhs, bit);
+            }
+        }
+        let mut solver = GF2Solver::attach(work, rowset.len());
+        raw.rank.push(solver.rank());
+        // The coefficient block is shared by the whole round, so one
+        // kernel basis serves every node solved in it.
+        let kernel = if minimize {
+            let kernel = solver.kernel_basis();
+            assert!(
+                kernel.len() < usize::BITS as usize,
+                "too many free variables"
+            );
+            kernel
+        } else {
+            Vec::new()
+        };
+        let mut out = FixedBitSet::with_capacity(colset.len());
+        let mut corrected = Vec::new();
+        for (ieq, &u) in rowset.iter().enumerate() {
+            // The first round is reserved for pinned nodes, which must
+            // all be corrected there.
+            if k == 1 && !pinned.is_empty() && !pinned.contains(&u) {
+                continue;
+            }
+            // A layer-pinned node only enters the solver in its round.
+            if fixed_layers.get(&u).is_some_and(|&l| l != k) {
+                continue;
+            }
+            if let Some(fu) = fixed.get(&u) {
+                if fixed_admissible(&g, &iset, &ocset, plane[&u], u, fu) {
+                    f.insert(u, fu.clone());
+                    layer[u] = k;
+                    corrected.push(u);
+                }
+                continue;
+            }
+            if !solver.solve_in_place(&mut out, ieq) {
+                continue;
+            }
+            for mask in 1..1usize << kernel.len() {
+                let mut x = out.clone();
+                for (i, v) in kernel.iter().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        x ^= v;
+                    }
+                }
+                if x.count_ones(..) < out.count_ones(..) {
+                    out = x;
+                }
+            }
+            let mut fu: Nodes = out.ones().map(|c| colset[c]).collect();
+            if plane[&u] != Plane::XY {
+                fu.insert(u);
+            }
+            raw.x.insert(u, out.clone());
+            f.insert(u, fu);
+            layer[u] = k;
+            corrected.push(u);
+        }
+        raw.stats.solves += solver.solve_count();
+        raw.stats.pivot_ops += solver.pivot_ops();
+        if corrected.is_empty() {
+            // A round may legitimately stay empty while every solvable
+            // node is pinned to a later layer; without such a pending
+            // pin the search has genuinely stalled.
+            let pending = fixed_layers
+                .iter()
+                .any(|(u, &l)| l > k && ocset.contains(u));
+            if !pending {
+                log::debug!("gflow round {k}: stalled with {} unsolved", rowset.len());
+                return Err(FindFailure::NoFlow {
+                    blocked: ocset,
+                    layer: k,
+                });
+            }
+        }
+        log::debug!("gflow round {k}: corrected {}", corrected.len());
+        if k == 1 && !pinned.is_empty() {
+            let blocked: Nodes = pinned
+                .iter()
+                .filter(|u| !corrected.contains(u))
+                .copied()
+                .collect();
+            if !blocked.is_empty() {
+                return Err(FindFailure::NoFlow { blocked, layer: k });
+            }
+        }
+        // A layer-pinned node missing its round can never be corrected.
+        let missed: Nodes = fixed_layers
+            .iter()
+            .filter(|&(u, &l)| l == k && !corrected.contains(u))
+            .map(|(&u, _)| u)
+            .collect();
+        if !missed.is_empty() {
+            return Err(FindFailure::NoFlow {
+                blocked: missed,
+                layer: k,
+            });
+        }
+        prev = corrected.iter().copied().collect();
+        for u in corrected {
+            ocset.remove(&u);
+        }
+    }
+    Ok((f, layer, raw))
+}
+
+/// Checks whether a caller-fixed correction set is admissible for `u`
+/// with the unprocessed nodes `ocset`: it may only contain processed
+/// non-inputs besides `u` itself, and its odd neighborhood must meet
+/// the unprocessed nodes exactly as the measurement plane demands.
+fn fixed_admissible(
+    g: &Graph,
+    iset: &Nodes,
+    ocset: &Nodes,
+    plane: Plane,
+    u: usize,
+    fu: &Nodes,
+) -> bool {
+    if fu.contains(&u) == (plane == Plane::XY) {
+        return false;
+    }
+    if fu
+        .iter()
+        .any(|&w| w != u && (ocset.contains(&w) || iset.contains(&w)))
+    {
+        return false;
+    }
+    let odd = crate::common::odd_neighbors(g, fu);
+    ocset.iter().all(|&w| {
+        let expected = w == u && plane != Plane::YZ;
+        odd.contains(&w) == expected
+    })
+}
+
+/// Finds a smallest input set among `candidates` admitting a gflow.
+///
+/// Inputs only restrict the available correction columns, so subsets
+/// are tried in order of increasing size with the finder as oracle and
+/// the first success is returned. The search is exponential in the
+/// number of candidates and meant for small graphs.
+///
+/// # Panics
+///
+/// Panics if there are more candidates than bits in a machine word.
+pub fn min_input_set(
+    g: &Graph,
+    oset: &Nodes,
+    plane: &HashMap<usize, Plane>,
+    candidates: &Nodes,
+) -> Option<Nodes> {
+    let candidates: Vec<usize> = candidates.iter().copied().collect();
+    assert!(candidates.len() < usize::BITS as usize, "too many candidates");
+    for size in 0..=candidates.len() {
+        for mask in 0..1usize << candidates.len() {
+            if mask.count_ones() as usize != size {
+                continue;
+            }
+            let iset: Nodes = candidates
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| mask & (1 << i) != 0)
+                .map(|(_, &u)| u)
+                .collect();
+            if find(g.clone(), iset.clone(), oset.clone(), plane.clone()).is_some() {
+                return Some(iset);
+            }
+        }
+    }
+    None
+}
+
+/// Checks whether two gflows on `g` implement the same computation.
+///
+/// Applying a correction set together with Z on its odd neighborhood
+/// realizes a graph stabilizer, so two correction sets for the same
+/// node are interchangeable iff their symmetric difference, and its odd
+/// neighborhood, only touch outputs or nodes measured strictly after
+/// that node under both flows.
+pub fn behaviorally_equivalent(g: &Graph, f1: &GFlow, f2: &GFlow) -> bool {
+    if f1.len() != f2.len() || f1.keys().any(|u| !f2.contains_key(u)) {
+        return false;
+    }
+    let Some(after1) = after_closure(g, f1) else {
+        return false;
+    };
+    let Some(after2) = after_closure(g, f2) else {
+        return false;
+    };
+    for (&u, fu1) in f1 {
+        let fu2 = &f2[&u];
+        let diff: Nodes = fu1.symmetric_difference(fu2).copied().collect();
+        let odd = crate::common::odd_neighbors(g, &diff);
+        for &w in diff.union(&odd) {
+            if w == u || !f1.contains_key(&w) {
+                continue;
+            }
+            if !after1[&u].contains(&w) || !after2[&u].contains(&w) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Computes, per measured node, the measured nodes forced strictly
+/// after it by the flow's dependency relation. Returns `None` if the
+/// relation is cyclic, i.e. the flow admits no measurement order.
+fn after_closure(g: &Graph, f: &GFlow) -> Option<HashMap<usize, Nodes>> {
+    let mut after = HashMap::new();
+    for &u in f.keys() {
+        let mut seen = Nodes::new();
+        let mut stack = vec![u];
+        while let Some(v) = stack.pop() {
+            let Some(fv) = f.get(&v) else {
+                continue;
+            };
+            let mut deps = crate::common::odd_neighbors(g, fv);
+            deps.extend(fv);
+            for &w in &deps {
+                if w != v && f.contains_key(&w) && seen.insert(w) {
+                    stack.push(w);
+                }
+            }
+        }
+        if seen.contains(&u) {
+            return None;
+        }
+        after.insert(u, seen);
+    }
+    Some(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::odd_neighbors;
+    use crate::test_utils::{self, nodeset, planes};
+
+    #[test]
+    fn test_find_line() {
+        // 0 - 1 - 2
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer) = find(g, nodeset([0]), nodeset([2]), plane).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_find_focused() {
+        // On the 4-line the plain finder leaves 2 in Odd(f(0));
+        // focusing folds f(2) in and the result still verifies.
+        let g = test_utils::graph(4, &[(0, 1), (1, 2), (2, 3)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY), (2, Plane::XY)]);
+        let (plain, _) = find(g.clone(), nodeset([0]), nodeset([3]), plane.clone()).unwrap();
+        assert_eq!(plain[&0], nodeset([1]));
+        let (f, layer) =
+            find_focused(g.clone(), nodeset([0]), nodeset([3]), plane.clone()).unwrap();
+        assert_eq!(f[&0], nodeset([1, 3]));
+        for (&u, fu) in &f {
+            let odd = crate::common::odd_neighbors(&g, fu);
+            assert!(odd.iter().all(|&w| w == u || w == 3));
+        }
+        verify(&g, &nodeset([0]), &nodeset([3]), &plane, &f, &layer).unwrap();
+    }
+
+    #[test]
+    fn test_find_extended() {
+        // The line 0-1-2 drains into output 2 while node 3 hangs off
+        // output 4 on its own: node 3 is forced to layer 1 by the
+        // delayed schedule but can be measured first.
+        let g = test_utils::graph(5, &[(0, 1), (1, 2), (3, 4)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY), (3, Plane::XY)]);
+        let (f, layer) =
+            find(g.clone(), nodeset([]), nodeset([2, 4]), plane.clone()).unwrap();
+        assert_eq!(layer, vec![2, 1, 0, 1, 0]);
+        let (fe, extended) =
+            find_extended(g.clone(), nodeset([]), nodeset([2, 4]), plane.clone()).unwrap();
+        assert_eq!(fe, f);
+        assert_eq!(extended, vec![2, 1, 0, 2, 0]);
+        // The extended layering is a valid schedule for the same flow.
+        verify(&g, &nodeset([]), &nodeset([2, 4]), &plane, &fe, &extended).unwrap();
+    }
+
+    #[test]
+    fn test_extend_with_node() {
+        // Line 0-1 drains into output 2; output 3 stands alone.
+        let g = test_utils::graph(4, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer) = find(g.clone(), nodeset([]), nodeset([2, 3]), plane.clone()).unwrap();
+        assert_eq!(layer, vec![2, 1, 0, 0]);
+        // Appending an output extends the gflow verbatim.
+        let g2 = test_utils::graph(5, &[(0, 1), (1, 2), (2, 4)]);
+        let (f2, layer2) = extend_with_node(
+            g2.clone(),
+            nodeset([]),
+            nodeset([2, 3, 4]),
+            plane.clone(),
+            &f,
+            &layer,
+        )
+        .unwrap();
+        assert_eq!(f2, f);
+        assert_eq!(layer2, vec![2, 1, 0, 0, 0]);
+        verify(&g2, &nodeset([]), &nodeset([2, 3, 4]), &plane, &f2, &layer2).unwrap();
+        // An XY node corrected by the lone output 3 joins layer 1 even
+        // though it now sits in the odd neighborhood of f(0) = {1}:
+        // node 0 lives in layer 2, safely after it.
+        let g3 = test_utils::graph(5, &[(0, 1), (1, 2), (3, 4), (1, 4)]);
+        let plane3 = planes([(0, Plane::XY), (1, Plane::XY), (4, Plane::XY)]);
+        let (f3, layer3) =
+            extend_with_node(g3.clone(), nodeset([]), nodeset([2, 3]), plane3.clone(), &f, &layer)
+                .unwrap();
+        assert_eq!(f3[&4], nodeset([3]));
+        assert_eq!(layer3, vec![2, 1, 0, 0, 1]);
+        verify(&g3, &nodeset([]), &nodeset([2, 3]), &plane3, &f3, &layer3).unwrap();
+        // A node no output can correct falls back to the full search,
+        // which also fails here.
+        let g4 = test_utils::graph(5, &[(0, 1), (1, 2), (2, 4)]);
+        let plane4 = planes([(0, Plane::XY), (1, Plane::XY), (4, Plane::XY)]);
+        assert!(
+            extend_with_node(g4, nodeset([]), nodeset([2, 3]), plane4, &f, &layer).is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_as_matrix() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer) = find(g.clone(), nodeset([0]), nodeset([2]), plane.clone()).unwrap();
+        let (mat, mat_layer) = find_as_matrix(g, nodeset([0]), nodeset([2]), plane).unwrap();
+        assert_eq!(mat_layer, layer);
+        for (u, row) in mat.iter().enumerate() {
+            let fu: Nodes = row.ones().collect();
+            assert_eq!(fu, f.get(&u).cloned().unwrap_or_default());
+        }
+    }
+
+    #[test]
+    fn test_find_batch() {
+        // One solvable line, one flowless triangle; order is preserved.
+        let line = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let triangle = test_utils::graph(3, &[(0, 1), (1, 2), (2, 0)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let results = find_batch(vec![
+            (line.clone(), nodeset([0]), nodeset([2]), plane.clone()),
+            (triangle, nodeset([0]), nodeset([2]), plane.clone()),
+        ]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], find(line, nodeset([0]), nodeset([2]), plane));
+        assert!(results[1].is_none());
+    }
+
+    #[test]
+    fn test_find_deterministic() {
+        // Both outputs could correct 0; the sorted column basis and the
+        // free-variables-zero rule always pick the smaller one.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let run = || find(g.clone(), nodeset([0]), nodeset([1, 2]), plane.clone()).unwrap();
+        assert_eq!(run(), run());
+        assert_eq!(run().0[&0], nodeset([1]));
+    }
+
+    #[test]
+    fn test_find_depth() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        assert_eq!(find_depth(g, nodeset([0]), nodeset([2]), plane), Some(2));
+    }
+
+    #[test]
+    fn test_find_with_max_depth() {
+        // The line needs two layers: a cap of 1 rejects it, a cap of 2
+        // reproduces the unrestricted flow.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        assert!(
+            find_with_max_depth(g.clone(), nodeset([0]), nodeset([2]), plane.clone(), 1).is_none()
+        );
+        let (f, layer) =
+            find_with_max_depth(g, nodeset([0]), nodeset([2]), plane, 2).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_find_multi_correction() {
+        // Node 3 needs a correction set of size two.
+        let g = test_utils::graph(4, &[(0, 1), (0, 2), (1, 3)]);
+        let plane = planes([(0, Plane::XY), (3, Plane::XY)]);
+        let (f, layer) = find(g.clone(), nodeset([]), nodeset([1, 2]), plane).unwrap();
+        assert_eq!(f[&0], nodeset([2]));
+        assert_eq!(f[&3], nodeset([1, 2]));
+        assert_eq!(layer, vec![1, 0, 0, 1]);
+        for (&u, fu) in &f {
+            let odd = odd_neighbors(&g, fu);
+            assert!(odd.contains(&u));
+            for &w in odd.iter().chain(fu) {
+                assert!(w == u || layer[w] < layer[u]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_next_layer_chain() {
+        // Corrections on a chain are naturally layer-adjacent.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer) = find_next_layer(g, nodeset([0]), nodeset([2]), plane).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_find_next_layer_long_range() {
+        // Node 0 can only be corrected by the output two layers down:
+        // the YZ node 1 blocks round one and is useless as a corrector.
+        let g = test_utils::graph(3, &[(0, 2), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::YZ)]);
+        let (f, layer) =
+            find(g.clone(), nodeset([]), nodeset([2]), plane.clone()).unwrap();
+        assert_eq!(f[&0], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+        assert!(find_next_layer(g, nodeset([]), nodeset([2]), plane).is_none());
+    }
+
+    #[test]
+    fn test_find_with_pinned() {
+        // Pinning 1 forces 0 a layer up and keeps 1 out of f(0).
+        let g = test_utils::graph(4, &[(0, 2), (0, 3), (1, 3)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer) =
+            find_with_pinned(g, nodeset([]), nodeset([2, 3]), plane, &nodeset([1])).unwrap();
+        assert_eq!(layer[1], 1);
+        assert_eq!(layer[0], 2);
+        assert!(!f[&0].contains(&1));
+    }
+
+    #[test]
+    fn test_find_with_pinned_unsatisfiable() {
+        // Node 0 cannot be corrected by outputs alone.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        assert!(
+            find_with_pinned(g, nodeset([]), nodeset([2]), plane, &nodeset([0])).is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_with_fixed() {
+        // Both {1} and {2} correct node 0; mandate the one the solver
+        // would not pick.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let fixed = GFlow::from([(0, nodeset([2]))]);
+        let (f, layer) =
+            find_with_fixed(g, nodeset([]), nodeset([1, 2]), plane, &fixed).unwrap();
+        assert_eq!(f[&0], nodeset([2]));
+        assert_eq!(layer, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_find_with_fixed_inconsistent() {
+        // {1, 2} has an empty odd neighborhood, so node 0 is never
+        // admissible.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let fixed = GFlow::from([(0, nodeset([1, 2]))]);
+        assert!(find_with_fixed(g, nodeset([]), nodeset([1, 2]), plane, &fixed).is_none());
+    }
+
+    #[test]
+    fn test_find_with_fixed_layers() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        // Pinning node 0 past its maximally-delayed layer 2 leaves
+        // round 2 empty; the other layers are unaffected.
+        let pins = HashMap::from([(0, 3)]);
+        let (f, layer) =
+            find_with_fixed_layers(g.clone(), nodeset([0]), nodeset([2]), plane.clone(), &pins)
+                .unwrap();
+        assert_eq!(layer, vec![3, 1, 0]);
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        // Node 0 cannot be corrected as early as layer 1.
+        let pins = HashMap::from([(0, 1)]);
+        assert!(
+            find_with_fixed_layers(g.clone(), nodeset([0]), nodeset([2]), plane.clone(), &pins)
+                .is_none()
+        );
+        // A measured node cannot sit in layer 0.
+        let pins = HashMap::from([(1, 0)]);
+        assert!(find_with_fixed_layers(g, nodeset([0]), nodeset([2]), plane, &pins).is_none());
+    }
+
+    #[test]
+    fn test_min_input_set() {
+        // The line admits a gflow with no inputs at all, which is the
+        // minimum.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let iset = min_input_set(&g, &nodeset([2]), &plane, &nodeset([0, 1])).unwrap();
+        assert_eq!(iset, nodeset([]));
+    }
+
+    #[test]
+    fn test_min_input_set_none() {
+        // No input choice helps an isolated measured node.
+        let g = test_utils::graph(2, &[]);
+        let plane = planes([(0, Plane::XY)]);
+        assert!(min_input_set(&g, &nodeset([1]), &plane, &nodeset([0])).is_none());
+    }
+
+    #[test]
+    fn test_behaviorally_equivalent() {
+        // The sets differ by the stabilizer of {2, 3}, supported on
+        // outputs only.
+        let g = test_utils::graph(4, &[(0, 1), (2, 3)]);
+        let f1 = GFlow::from([(0, nodeset([1]))]);
+        let f2 = GFlow::from([(0, nodeset([1, 2, 3]))]);
+        assert!(behaviorally_equivalent(&g, &f1, &f2));
+        assert!(behaviorally_equivalent(&g, &f2, &f1));
+    }
+
+    #[test]
+    fn test_behaviorally_inequivalent() {
+        // The difference {3} has measured node 1 in its odd
+        // neighborhood, which f2 does not order after 0.
+        let g = test_utils::graph(4, &[(0, 2), (1, 2), (1, 3)]);
+        let f1 = GFlow::from([(0, nodeset([2])), (1, nodeset([3]))]);
+        let f2 = GFlow::from([(0, nodeset([2, 3])), (1, nodeset([3]))]);
+        assert!(!behaviorally_equivalent(&g, &f1, &f2));
+    }
+
+    #[test]
+    fn test_behaviorally_equivalent_domain_mismatch() {
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let f1 = GFlow::from([(0, nodeset([1]))]);
+        assert!(!behaviorally_equivalent(&g, &f1, &GFlow::new()));
+    }
+
+    #[test]
+    fn test_verify_accepts_finder_output() {
+        let g = test_utils::graph(4, &[(0, 1), (0, 2), (1, 3)]);
+        let plane = planes([(0, Plane::XY), (3, Plane::XY)]);
+        let (f, layer) =
+            find(g.clone(), nodeset([]), nodeset([1, 2]), plane.clone()).unwrap();
+        verify(&g, &nodeset([]), &nodeset([1, 2]), &plane, &f, &layer).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_unordered_correction() {
+        // Both nodes claim the same layer, so neither correction is
+        // ordered after its node.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let f = GFlow::from([(0, nodeset([1])), (1, nodeset([2]))]);
+        let err = verify(&g, &nodeset([0]), &nodeset([2]), &plane, &f, &vec![1, 1, 0])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("correction set of 0 not ordered after it: 1"));
+    }
+
+    #[test]
+    fn test_find_unique() {
+        // A single wire admits exactly one correction per node.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let plane = planes([(0, Plane::XY)]);
+        let result = find_unique(g, nodeset([0]), nodeset([1]), plane).unwrap();
+        let expected = GFlow::from([(0, nodeset([1]))]);
+        assert_eq!(result, Uniqueness::Unique(expected, vec![1, 0]));
+    }
+
+    #[test]
+    fn test_find_unique_ambiguous() {
+        // Node 0 can be corrected by {1} or {2}.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let result = find_unique(g, nodeset([]), nodeset([1, 2]), plane).unwrap();
+        assert_eq!(result, Uniqueness::Ambiguous(nodeset([0])));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        // The single wire is fully determined; node 0 of the fork has
+        // one free variable, hence two correction sets.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let plane = planes([(0, Plane::XY)]);
+        let counts = count_solutions(g, nodeset([0]), nodeset([1]), plane).unwrap();
+        assert_eq!(counts, HashMap::from([(0, 1)]));
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let counts = count_solutions(g, nodeset([0]), nodeset([1, 2]), plane).unwrap();
+        assert_eq!(counts, HashMap::from([(0, 2)]));
+    }
+
+    #[test]
+    fn test_find_trivial() {
+        // All nodes are outputs: the empty flow of depth 0.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let (f, layer) = find(g, nodeset([0]), nodeset([0, 1]), planes([])).unwrap();
+        assert!(f.is_empty());
+        assert_eq!(layer, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_find_with_reason() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let ok = find_with_reason(g.clone(), nodeset([0]), nodeset([2]), plane.clone(), None);
+        assert_eq!(ok, Ok(find(g.clone(), nodeset([0]), nodeset([2]), plane.clone()).unwrap()));
+        assert_eq!(
+            find_with_reason(g, nodeset([0]), nodeset([2]), plane, Some(1)),
+            Err(FindFailure::DepthExceeded)
+        );
+        assert_eq!(
+            find_with_reason(Vec::new(), nodeset([]), nodeset([]), planes([]), None),
+            Err(FindFailure::EmptyGraph)
+        );
+        // Isolated measured node cannot be corrected; the failure names
+        // it before the first round instead of stalling in it.
+        let g = test_utils::graph(2, &[]);
+        let plane = planes([(0, Plane::XY)]);
+        assert_eq!(
+            find_with_reason(g, nodeset([]), nodeset([1]), plane, None),
+            Err(FindFailure::NoFlow {
+                blocked: nodeset([0]),
+                layer: 0
+            })
+        );
+        // Inputs outnumbering the outputs are rejected up front.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let plane = planes([(0, Plane::XY)]);
+        assert_eq!(
+            find_with_reason(g, nodeset([0, 1]), nodeset([1]), plane, None),
+            Err(FindFailure::TooManyInputs)
+        );
+    }
+
+    #[test]
+    fn test_find_isolated_node() {
+        // The isolated XY node 2 dooms the search even though the 0-1
+        // pair alone has a fine gflow; an isolated YZ node corrects
+        // itself and is no obstacle.
+        let g = test_utils::graph(3, &[(0, 1)]);
+        let plane = planes([(0, Plane::XY), (2, Plane::XY)]);
+        assert_eq!(
+            find_with_reason(g.clone(), nodeset([]), nodeset([1]), plane, None),
+            Err(FindFailure::NoFlow {
+                blocked: nodeset([2]),
+                layer: 0
+            })
+        );
+        let plane = planes([(0, Plane::XY), (2, Plane::YZ)]);
+        let (f, layer) = find(g, nodeset([]), nodeset([1]), plane).unwrap();
+        assert_eq!(f[&2], nodeset([2]));
+        assert_eq!(layer, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn test_find_min_weight() {
+        // Free-variables-zero hands node 0 the correction {1, 2}; the
+        // kernel scan finds the lighter {3} without touching the
+        // layering.
+        let g = test_utils::graph(5, &[(0, 2), (0, 3), (4, 1), (4, 2)]);
+        let plane = planes([(0, Plane::XY), (4, Plane::XY)]);
+        let iset = nodeset([0]);
+        let oset = nodeset([1, 2, 3]);
+        let (f, layer) = find(g.clone(), iset.clone(), oset.clone(), plane.clone()).unwrap();
+        assert_eq!(f[&0], nodeset([1, 2]));
+        let (fmin, lmin) = find_min_weight(g, iset, oset, plane).unwrap();
+        assert_eq!(fmin[&0], nodeset([3]));
+        assert_eq!(fmin[&4], nodeset([1]));
+        assert_eq!(lmin, layer);
+    }
+
+    #[test]
+    fn test_enumerate_solutions() {
+        // Node 0 of the fork can be corrected by {1} or {2} but not by
+        // {1, 2}; the finder's choice is listed first.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let plane = planes([(0, Plane::XY)]);
+        let sols =
+            enumerate_solutions(g.clone(), nodeset([]), nodeset([1, 2]), plane.clone(), 0, 8)
+                .unwrap();
+        assert_eq!(sols, vec![nodeset([1]), nodeset([2])]);
+        let sols = enumerate_solutions(g, nodeset([]), nodeset([1, 2]), plane, 0, 1).unwrap();
+        assert_eq!(sols, vec![nodeset([1])]);
+    }
+
+    #[test]
+    fn test_find_raw_decodes() {
+        // Decoding each bitvector through its round's basis, plus the
+        // node itself off the XY plane, reproduces the correction sets.
+        let g = test_utils::graph(4, &[(0, 1), (0, 2), (1, 3)]);
+        let plane = planes([(0, Plane::XY), (3, Plane::YZ)]);
+        let (f, layer, raw) = find_raw(g, nodeset([]), nodeset([1, 2]), plane.clone()).unwrap();
+        assert_eq!(raw.x.len(), f.len());
+        for (&u, x) in &raw.x {
+            let basis = &raw.tab[layer[u] - 1];
+            let mut fu: Nodes = x.ones().map(|c| basis[c]).collect();
+            if plane[&u] != Plane::XY {
+                fu.insert(u);
+            }
+            assert_eq!(fu, f[&u]);
+        }
+    }
+
+    #[test]
+    fn test_find_with_solve_stats() {
+        // Both measured nodes are solved in round 1, one solver call
+        // each. The coefficient rows [1, 1] and [1, 0] share column 0
+        // and pivoting column 1 hits the first row again, so the
+        // elimination performs exactly two row additions.
+        let g = test_utils::graph(4, &[(0, 2), (0, 3), (1, 2)]);
+        let plane = planes([(0, Plane::XY), (1, Plane::XY)]);
+        let (f, layer, stats) =
+            find_with_solve_stats(g.clone(), nodeset([]), nodeset([2, 3]), plane.clone()).unwrap();
+        let (f2, layer2) = find(g, nodeset([]), nodeset([2, 3]), plane).unwrap();
+        assert_eq!(f, f2);
+        assert_eq!(layer, layer2);
+        assert_eq!(
+            stats,
+            SolveStats {
+                solves: 2,
+                pivot_ops: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_yz_plane() {
+        // A YZ-measured node corrects through itself.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let plane = planes([(0, Plane::YZ)]);
+        let (f, layer) = find(g, nodeset([]), nodeset([1]), plane).unwrap();
+        assert_eq!(f[&0], nodeset([0]));
+        assert_eq!(layer, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_find_none() {
+        // Isolated measured node cannot be corrected.
+        let g = test_utils::graph(2, &[]);
+        let plane = planes([(0, Plane::XY)]);
+        assert!(find(g, nodeset([]), nodeset([1]), plane).is_none());
+    }
+}
@@ -0,0 +1,873 @@
+//! Rust binding of generalized and Pauli flow finding algorithms.
+
+use std::collections::HashMap;
+
+use numpy::{PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::Borrowed;
+
+pub mod common;
+pub mod flow;
+pub mod gf2_linalg;
+pub mod gflow;
+pub mod pflow;
+#[cfg(test)]
+pub mod test_utils;
+
+use crate::common::{Layer, Nodes};
+use crate::gflow::Plane;
+use crate::pflow::{Branch, PPlane};
+
+pyo3::create_exception!(
+    fastflow,
+    EmptyGraphError,
+    PyValueError,
+    "The graph has no nodes; there is nothing to search."
+);
+pyo3::create_exception!(
+    fastflow,
+    NoFlowError,
+    pyo3::exceptions::PyException,
+    "No flow of the requested kind exists for the open graph."
+);
+pyo3::create_exception!(
+    fastflow,
+    DepthExceededError,
+    pyo3::exceptions::PyException,
+    "No flow exists within the requested depth bound."
+);
+
+/// Decodes the wire format of a measurement plane.
+fn plane_from_u8(value: u8) -> PyResult<Plane> {
+    match value {
+        0 => Ok(Plane::XY),
+        1 => Ok(Plane::YZ),
+        2 => Ok(Plane::XZ),
+        _ => Err(PyValueError::new_err(format!("invalid plane: {value}"))),
+    }
+}
+
+/// Argument accepting the exported [`Plane`] enum or its wire-format
+/// integer, keeping callers of the integer convention working.
+struct PlaneLike(Plane);
+
+impl<'a, 'py> FromPyObject<'a, 'py> for PlaneLike {
+    type Error = PyErr;
+
+    fn extract(ob: Borrowed<'a, 'py, PyAny>) -> PyResult<Self> {
+        if let Ok(p) = ob.extract::<Plane>() {
+            return Ok(Self(p));
+        }
+        Ok(Self(plane_from_u8(ob.extract::<u8>()?)?))
+    }
+}
+
+/// Argument accepting the exported [`PPlane`] enum or its wire-format
+/// integer; see [`PlaneLike`].
+struct PPlaneLike(PPlane);
+
+impl<'a, 'py> FromPyObject<'a, 'py> for PPlaneLike {
+    type Error = PyErr;
+
+    fn extract(ob: Borrowed<'a, 'py, PyAny>) -> PyResult<Self> {
+        if let Ok(p) = ob.extract::<PPlane>() {
+            return Ok(Self(p));
+        }
+        Ok(Self(pplane_from_u8(ob.extract::<u8>()?)?))
+    }
+}
+
+/// Decodes the wire format of a correction branch.
+fn branch_from_u8(value: u8) -> PyResult<Branch> {
+    match value {
+        0 => Ok(Branch::XY),
+        1 => Ok(Branch::YZ),
+        2 => Ok(Branch::XZ),
+        _ => Err(PyValueError::new_err(format!("invalid branch: {value}"))),
+    }
+}
+
+/// Encodes a correction branch in the wire format.
+fn branch_to_u8(branch: Branch) -> u8 {
+    match branch {
+        Branch::XY => 0,
+        Branch::YZ => 1,
+        Branch::XZ => 2,
+    }
+}
+
+/// Decodes the wire format of a measurement plane or Pauli axis.
+fn pplane_from_u8(value: u8) -> PyResult<PPlane> {
+    match value {
+        0 => Ok(PPlane::XY),
+        1 => Ok(PPlane::YZ),
+        2 => Ok(PPlane::XZ),
+        3 => Ok(PPlane::X),
+        4 => Ok(PPlane::Y),
+        5 => Ok(PPlane::Z),
+        _ => Err(PyValueError::new_err(format!("invalid pplane: {value}"))),
+    }
+}
+
+/// Forwards `log` records to Python's `logging` module.
+///
+/// Each record attaches to the interpreter and lands on the logger
+/// named after its target (e.g. `fastflow::gflow`), so the standard
+/// `logging` configuration applies. Installed once by
+/// [`set_log_level`].
+struct PyLogBridge;
+
+static LOG_BRIDGE: PyLogBridge = PyLogBridge;
+
+impl log::Log for PyLogBridge {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let msg = record.args().to_string();
+        // Python logging levels: ERROR 40, WARNING 30, INFO 20, DEBUG 10.
+        let pylevel: u8 = match record.level() {
+            log::Level::Error => 40,
+            log::Level::Warn => 30,
+            log::Level::Info => 20,
+            log::Level::Debug => 10,
+            log::Level::Trace => 5,
+        };
+        let target = record.target().to_string();
+        Python::attach(|py| {
+            let _ = py
+                .import("logging")
+                .and_then(|m| m.call_method1("getLogger", (target,)))
+                .and_then(|l| l.call_method1("log", (pylevel, msg)));
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Routes the Rust-side trace output through Python's `logging` module
+/// at the given level.
+///
+/// `level` is one of `"off"`, `"error"`, `"warn"`, `"info"`, `"debug"`
+/// or `"trace"`, case-insensitive. May be called repeatedly to change
+/// the level; the bridge itself is installed once per process.
+#[pyfunction]
+fn set_log_level(level: &str) -> PyResult<()> {
+    let filter: log::LevelFilter = level
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("invalid log level: {level}")))?;
+    // A second install attempt only means the bridge is already active.
+    let _ = log::set_logger(&LOG_BRIDGE);
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Expresses a layering as absolute measurement times.
+#[pyfunction]
+fn absolute_schedule(
+    layer: Layer,
+    output_times: HashMap<usize, f64>,
+    layer_duration: f64,
+) -> HashMap<usize, f64> {
+    common::absolute_schedule(&layer, &output_times, layer_duration)
+}
+
+/// Packs the adjacency of each node into a row of set-bit indices.
+#[pyfunction]
+fn adjacency_bitsets(g: Vec<Nodes>) -> Vec<Vec<usize>> {
+    common::adjacency_bitsets(&g)
+        .iter()
+        .map(|row| row.ones().collect())
+        .collect()
+}
+
+/// Checks that a map assigns a value to exactly the measured nodes,
+/// i.e. its keys are `0..n` minus `oset`. Values are ignored; on
+/// mismatch the error lists every missing and extra node.
+#[pyfunction]
+fn check_domain(map: HashMap<usize, Py<PyAny>>, n: usize, oset: Nodes) -> PyResult<()> {
+    let vset: Nodes = (0..n).collect();
+    common::check_domain(&map, &vset, &oset).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Checks that a layering is consistent with the outputs: every node
+/// of `oset` must sit in layer `0`.
+#[pyfunction]
+fn check_initial(layer: Layer, oset: Nodes) -> PyResult<()> {
+    if let Some(&u) = oset.iter().find(|&&u| u >= layer.len()) {
+        return Err(PyValueError::new_err(format!(
+            "oset is not a subset of the nodes: {u}"
+        )));
+    }
+    common::check_initial(&layer, &oset).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Checks that a graph is a valid undirected adjacency structure:
+/// symmetric, free of self-loops, with in-range neighbor indices.
+#[pyfunction]
+fn validate_graph(g: Vec<Nodes>) -> PyResult<()> {
+    common::validate_graph(&g).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Computes the simple-graph complement.
+#[pyfunction]
+fn complement(g: Vec<Nodes>) -> Vec<Nodes> {
+    common::complement(&g)
+}
+
+/// Counts, per node, how many correction sets include it.
+#[pyfunction]
+fn correction_fan_in(f: HashMap<usize, Nodes>) -> HashMap<usize, usize> {
+    common::correction_fan_in(&f)
+}
+
+/// Counts the nodes of each layer; entry `k` is the width of layer `k`.
+#[pyfunction]
+fn layer_widths(layer: Layer) -> Vec<usize> {
+    common::layer_widths(&layer)
+}
+
+/// Computes the size of the largest layer, i.e. the maximum number of
+/// qubits measured simultaneously.
+#[pyfunction]
+fn max_width(layer: Layer) -> usize {
+    common::max_width(&layer)
+}
+
+/// Flattens a layering into a total measurement order: deepest layer
+/// first, increasing node index within a layer, outputs last.
+#[pyfunction]
+fn layer_to_order(layer: Layer, oset: Nodes) -> Vec<usize> {
+    common::layer_to_order(&layer, &oset)
+}
+
+/// Re-expresses a correction function with deterministic ordering: the
+/// dict is ordered by corrected node and each correction set becomes a
+/// sorted list, for stable diffs and golden-file tests.
+#[pyfunction]
+fn sorted_corrections(f: HashMap<usize, Nodes>) -> std::collections::BTreeMap<usize, Vec<usize>> {
+    common::sorted_corrections(&f)
+}
+
+/// Computes the cycle rank (number of independent cycles) of a graph.
+#[pyfunction]
+fn cycle_rank(g: Vec<Nodes>) -> usize {
+    common::cycle_rank(&g)
+}
+
+/// Builds the symmetric adjacency structure from an edge list.
+#[pyfunction]
+fn graph_from_edges(n: usize, edges: Vec<(usize, usize)>) -> PyResult<Vec<Nodes>> {
+    common::graph_from_edges(n, &edges).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Extracts the adjacency of a `networkx.Graph` into the internal
+/// adjacency structure.
+///
+/// Node labels must be exactly the integers `0..n` where `n` is the
+/// number of nodes; relabel with
+/// `networkx.convert_node_labels_to_integers` first if needed. The
+/// resulting graph is validated together with `iset` and `oset` like
+/// every other entry point.
+#[pyfunction]
+fn from_networkx(nx_graph: &Bound<'_, PyAny>, iset: Nodes, oset: Nodes) -> PyResult<Vec<Nodes>> {
+    let n: usize = nx_graph.call_method0("number_of_nodes")?.extract()?;
+    for node in nx_graph.call_method0("nodes")?.try_iter()? {
+        let node = node?;
+        let u: usize = node.extract().map_err(|_| {
+            PyValueError::new_err(format!("node label is not an integer in 0..{n}: {node}"))
+        })?;
+        if u >= n {
+            return Err(PyValueError::new_err(format!(
+                "node label is not an integer in 0..{n}: {u}"
+            )));
+        }
+    }
+    let mut edges = Vec::new();
+    for edge in nx_graph.call_method0("edges")?.try_iter()? {
+        edges.push(edge?.extract::<(usize, usize)>()?);
+    }
+    let g = common::graph_from_edges(n, &edges).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    common::check_graph(&g, &iset, &oset).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(g)
+}
+
+/// Builds a `networkx.DiGraph` encoding the measurement order implied
+/// by a layering.
+///
+/// Deeper layers are measured first, so the DAG has an edge from each
+/// node to every node of the next shallower non-empty layer; its
+/// transitive closure is the full partial order `layer[u] > layer[v]`.
+#[pyfunction]
+fn partial_order_to_networkx(py: Python<'_>, layer: Layer) -> PyResult<Py<PyAny>> {
+    let nx = py.import("networkx")?;
+    let dag = nx.getattr("DiGraph")?.call0()?;
+    dag.call_method1("add_nodes_from", ((0..layer.len()).collect::<Vec<_>>(),))?;
+    let depth = layer.iter().copied().max().unwrap_or(0);
+    let mut buckets = vec![Vec::new(); depth + 1];
+    for (u, &l) in layer.iter().enumerate() {
+        buckets[l].push(u);
+    }
+    let filled: Vec<&Vec<usize>> = buckets.iter().rev().filter(|b| !b.is_empty()).collect();
+    let mut edges = Vec::new();
+    for pair in filled.windows(2) {
+        for &u in pair[0] {
+            for &v in pair[1] {
+                edges.push((u, v));
+            }
+        }
+    }
+    dag.call_method1("add_edges_from", (edges,))?;
+    Ok(dag.unbind())
+}
+
+/// Solves `Ax = b` over GF(2).
+///
+/// Returns the solution with all free variables zero, or `None` when
+/// the system is inconsistent. Exposes [`gf2_linalg::GF2Solver`] for
+/// callers that only need a one-shot solve.
+#[pyfunction]
+fn gf2_solve(matrix: Vec<Vec<bool>>, rhs: Vec<bool>) -> PyResult<Option<Vec<bool>>> {
+    let rows = matrix.len();
+    if rows == 0 || rhs.len() != rows {
+        return Err(PyValueError::new_err("matrix and rhs height mismatch"));
+    }
+    let cols = matrix[0].len();
+    if cols == 0 || matrix.iter().any(|row| row.len() != cols) {
+        return Err(PyValueError::new_err("matrix rows have inconsistent widths"));
+    }
+    let mut work = vec![fixedbitset::FixedBitSet::with_capacity(cols + 1); rows];
+    for (r, row) in matrix.iter().enumerate() {
+        for (c, &b) in row.iter().enumerate() {
+            work[r].set(c, b);
+        }
+        work[r].set(cols, rhs[r]);
+    }
+    let mut solver = gf2_linalg::GF2Solver::attach(work, 1);
+    let mut out = fixedbitset::FixedBitSet::with_capacity(cols);
+    if !solver.solve_in_place(&mut out, 0) {
+        return Ok(None);
+    }
+    Ok(Some((0..cols).map(|c| out.contains(c)).collect()))
+}
+
+/// Packs a flow's correction sets into compressed-sparse-row form.
+#[pyfunction]
+fn flow_to_csr(f: HashMap<usize, Nodes>, n: usize) -> (Vec<usize>, Vec<usize>) {
+    common::flow_to_csr(&f, n)
+}
+
+/// Hashes the coarse structure of a flow for cheap deduplication.
+#[pyfunction]
+fn flow_signature(f: HashMap<usize, Nodes>, layer: Layer) -> u64 {
+    common::flow_signature(&f, &layer)
+}
+
+/// Computes the odd neighborhood of a node set.
+#[pyfunction]
+fn odd_neighbors(g: Vec<Nodes>, nodes: Nodes) -> Nodes {
+    common::odd_neighbors(&g, &nodes)
+}
+
+/// Computes the symmetric difference of two neighborhoods, excluding
+/// the nodes themselves.
+#[pyfunction]
+fn neighborhood_symdiff(g: Vec<Nodes>, u: usize, v: usize) -> Nodes {
+    common::neighborhood_symdiff(&g, u, v)
+}
+
+/// Runs the input checks of a finder, surfacing a failure as a Python
+/// exception instead of the finder's panic.
+fn precheck<T>(
+    g: &common::Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    map: Option<&HashMap<usize, T>>,
+) -> PyResult<()> {
+    common::check_graph(g, iset, oset).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    if let Some(map) = map {
+        let vset: Nodes = (0..g.len()).collect();
+        common::check_domain(map, &vset, oset).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Checks the inputs and runs the causal-flow search on native Rust
+/// types.
+///
+/// This is the entry point behind [`find_flow`], without pyo3 argument
+/// conversion or interpreter handling: link the crate as an rlib to
+/// benchmark or unit-test the search in isolation. Invalid inputs
+/// surface as an `Err` instead of the finder's panic.
+pub fn find_flow_impl(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+) -> anyhow::Result<Option<(HashMap<usize, usize>, Layer)>> {
+    common::check_graph(&g, &iset, &oset)?;
+    Ok(flow::find(g, iset, oset))
+}
+
+/// Checks the inputs and runs the gflow search on native Rust types;
+/// see [`find_flow_impl`].
+pub fn find_gflow_impl(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, Plane>,
+) -> anyhow::Result<Option<(HashMap<usize, Nodes>, Layer)>> {
+    common::check_graph(&g, &iset, &oset)?;
+    let vset: Nodes = (0..g.len()).collect();
+    common::check_domain(&plane, &vset, &oset)?;
+    Ok(gflow::find(g, iset, oset, plane))
+}
+
+/// Checks the inputs and runs the Pauli-flow search on native Rust
+/// types; see [`find_flow_impl`].
+pub fn find_pflow_impl(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> anyhow::Result<Option<(HashMap<usize, Nodes>, Layer)>> {
+    common::check_graph(&g, &iset, &oset)?;
+    let vset: Nodes = (0..g.len()).collect();
+    common::check_domain(&pplane, &vset, &oset)?;
+    Ok(pflow::find(g, iset, oset, pplane))
+}
+
+/// Finds a maximally-delayed causal flow.
+///
+/// The search runs with the interpreter detached: the inputs are owned Rust values by
+/// the time it starts, so other Python threads may run meanwhile.
+#[pyfunction]
+fn find_flow(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+) -> PyResult<Option<(HashMap<usize, usize>, Layer)>> {
+    py.detach(|| find_flow_impl(g, iset, oset))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed generalized flow.
+///
+/// The search releases the GIL; see [`find_flow`].
+#[pyfunction]
+fn find_gflow(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, PlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let plane: HashMap<_, _> = plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    py.detach(|| find_gflow_impl(g, iset, oset, plane))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed generalized flow, raising a distinct
+/// exception per failure mode.
+///
+/// Raises `EmptyGraphError`, `NoFlowError`, or `DepthExceededError`
+/// instead of returning `None`, so callers can tell a degenerate input
+/// from a genuinely flowless graph or a too-tight `max_depth`.
+#[pyfunction]
+#[pyo3(signature = (g, iset, oset, plane, max_depth=None))]
+fn find_gflow_with_reason(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, PlaneLike>,
+    max_depth: Option<usize>,
+) -> PyResult<(HashMap<usize, Nodes>, Layer)> {
+    let plane: HashMap<_, _> = plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    precheck(&g, &iset, &oset, Some(&plane))?;
+    py.detach(|| gflow::find_with_reason(g, iset, oset, plane, max_depth))
+        .map_err(|reason| match reason {
+            gflow::FindFailure::EmptyGraph => EmptyGraphError::new_err("graph has no nodes"),
+            gflow::FindFailure::NoFlow { blocked, layer } => {
+                let mut blocked: Vec<usize> = blocked.into_iter().collect();
+                blocked.sort_unstable();
+                NoFlowError::new_err(format!(
+                    "no gflow exists: stalled at layer {layer} with nodes {blocked:?} unsolved"
+                ))
+            }
+            gflow::FindFailure::TooManyInputs => {
+                NoFlowError::new_err("more inputs than outputs: no gflow can exist")
+            }
+            gflow::FindFailure::DepthExceeded => {
+                DepthExceededError::new_err("no gflow within the depth bound")
+            }
+        })
+}
+
+/// Finds maximally-delayed gflows for a batch of inputs in parallel.
+///
+/// The batch runs on the rayon pool with the interpreter detached;
+/// results come back in input order.
+#[pyfunction]
+#[allow(clippy::type_complexity)]
+fn find_gflow_batch(
+    py: Python<'_>,
+    inputs: Vec<(Vec<Nodes>, Nodes, Nodes, HashMap<usize, PlaneLike>)>,
+) -> PyResult<Vec<Option<(HashMap<usize, Nodes>, Layer)>>> {
+    let inputs = inputs
+        .into_iter()
+        .map(|(g, iset, oset, plane)| {
+            let plane: HashMap<_, _> =
+                plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+            precheck(&g, &iset, &oset, Some(&plane))?;
+            Ok((g, iset, oset, plane))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(py.detach(|| gflow::find_batch(inputs)))
+}
+
+/// Finds a maximally-delayed Pauli flow.
+///
+/// The search releases the GIL; see [`find_flow`].
+#[pyfunction]
+fn find_pflow(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    py.detach(|| find_pflow_impl(g, iset, oset, pplane))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed Pauli flow under a wall-clock deadline.
+///
+/// Raises `TimeoutError` when the search does not finish within
+/// `timeout_ms` milliseconds; see [`find_flow`] for the GIL handling.
+#[pyfunction]
+fn find_pflow_with_timeout(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+    timeout_ms: u64,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    precheck(&g, &iset, &oset, Some(&pplane))?;
+    let interrupt = pflow::Interrupt {
+        deadline: Some(std::time::Instant::now() + std::time::Duration::from_millis(timeout_ms)),
+        cancel: None,
+    };
+    py.detach(|| pflow::find_with_interrupt(g, iset, oset, pplane, interrupt))
+        .map_err(|pflow::Interrupted| {
+            pyo3::exceptions::PyTimeoutError::new_err("Pauli flow search timed out")
+        })
+}
+
+/// Finds a maximally-delayed Pauli flow, invoking `callback(layer,
+/// solved, remaining)` after each completed layer.
+///
+/// The search runs with the interpreter detached; the callback
+/// re-attaches for each invocation, so it may update progress bars or
+/// log. An exception raised by the callback is reported once the
+/// search finishes.
+#[pyfunction]
+fn find_pflow_with_progress(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+    callback: Py<PyAny>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    precheck(&g, &iset, &oset, Some(&pplane))?;
+    let mut callback_error: Option<PyErr> = None;
+    let result = py.detach(|| {
+        let mut on_layer = |k: usize, solved: usize, remaining: usize| {
+            if callback_error.is_some() {
+                return;
+            }
+            Python::attach(|py| {
+                if let Err(e) = callback.call1(py, (k, solved, remaining)) {
+                    callback_error = Some(e);
+                }
+            });
+        };
+        pflow::find_with_progress(g, iset, oset, pplane, &mut on_layer)
+    });
+    match callback_error {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// Builds the internal adjacency structure from a dense boolean
+/// adjacency matrix, rejecting asymmetry and a nonzero diagonal.
+fn graph_from_adjacency(adj: &PyReadonlyArray2<bool>) -> PyResult<common::Graph> {
+    let adj = adj.as_array();
+    let (rows, cols) = adj.dim();
+    if rows != cols {
+        return Err(PyValueError::new_err("adjacency matrix is not square"));
+    }
+    let mut g = vec![Nodes::new(); rows];
+    for i in 0..rows {
+        if adj[(i, i)] {
+            return Err(PyValueError::new_err(format!("self-loop detected: {i}")));
+        }
+        for j in (i + 1)..cols {
+            if adj[(i, j)] != adj[(j, i)] {
+                return Err(PyValueError::new_err(format!(
+                    "adjacency matrix is not symmetric: {i}-{j}"
+                )));
+            }
+            if adj[(i, j)] {
+                g[i].insert(j);
+                g[j].insert(i);
+            }
+        }
+    }
+    Ok(g)
+}
+
+/// Finds a maximally-delayed gflow on a graph given as a dense boolean
+/// adjacency matrix, read directly from numpy without a Python-side
+/// conversion.
+#[pyfunction]
+fn find_gflow_from_adjacency(
+    py: Python<'_>,
+    adj: PyReadonlyArray2<bool>,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, PlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let g = graph_from_adjacency(&adj)?;
+    let plane: HashMap<_, _> = plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    precheck(&g, &iset, &oset, Some(&plane))?;
+    Ok(py.detach(|| gflow::find(g, iset, oset, plane)))
+}
+
+/// Finds a maximally-delayed Pauli flow on a graph given as a dense
+/// boolean adjacency matrix; see [`find_gflow_from_adjacency`].
+#[pyfunction]
+fn find_pflow_from_adjacency(
+    py: Python<'_>,
+    adj: PyReadonlyArray2<bool>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let g = graph_from_adjacency(&adj)?;
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    precheck(&g, &iset, &oset, Some(&pplane))?;
+    Ok(py.detach(|| pflow::find(g, iset, oset, pplane)))
+}
+
+/// Converts a boolean mask over the nodes into a node set, rejecting a
+/// length that does not match the node count.
+fn nodes_from_mask(mask: &PyReadonlyArray1<bool>, n: usize) -> PyResult<Nodes> {
+    let mask = mask.as_array();
+    if mask.len() != n {
+        return Err(PyValueError::new_err(format!(
+            "mask length {} does not match the node count {n}",
+            mask.len()
+        )));
+    }
+    Ok(mask
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b)
+        .map(|(u, _)| u)
+        .collect())
+}
+
+/// Finds a maximally-delayed causal flow with the input and output
+/// sets given as boolean masks of length `n`, read directly from
+/// numpy; see [`find_flow`].
+#[pyfunction]
+fn find_flow_masked(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    imask: PyReadonlyArray1<bool>,
+    omask: PyReadonlyArray1<bool>,
+) -> PyResult<Option<(HashMap<usize, usize>, Layer)>> {
+    let iset = nodes_from_mask(&imask, g.len())?;
+    let oset = nodes_from_mask(&omask, g.len())?;
+    py.detach(|| find_flow_impl(g, iset, oset))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed generalized flow with the input and
+/// output sets given as boolean masks; see [`find_flow_masked`].
+#[pyfunction]
+fn find_gflow_masked(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    imask: PyReadonlyArray1<bool>,
+    omask: PyReadonlyArray1<bool>,
+    plane: HashMap<usize, PlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let iset = nodes_from_mask(&imask, g.len())?;
+    let oset = nodes_from_mask(&omask, g.len())?;
+    let plane: HashMap<_, _> = plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    py.detach(|| find_gflow_impl(g, iset, oset, plane))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed Pauli flow with the input and output sets
+/// given as boolean masks; see [`find_flow_masked`].
+#[pyfunction]
+fn find_pflow_masked(
+    py: Python<'_>,
+    g: Vec<Nodes>,
+    imask: PyReadonlyArray1<bool>,
+    omask: PyReadonlyArray1<bool>,
+    pplane: HashMap<usize, PPlaneLike>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let iset = nodes_from_mask(&imask, g.len())?;
+    let oset = nodes_from_mask(&omask, g.len())?;
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    py.detach(|| find_pflow_impl(g, iset, oset, pplane))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Finds a maximally-delayed Pauli flow with forced branches.
+#[pyfunction]
+fn find_pflow_with_branches(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+    forced_branches: HashMap<usize, u8>,
+) -> PyResult<Option<(HashMap<usize, Nodes>, Layer)>> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    let forced = forced_branches
+        .into_iter()
+        .map(|(u, b)| Ok((u, branch_from_u8(b)?)))
+        .collect::<PyResult<HashMap<_, _>>>()?;
+    Ok(pflow::find_with_branches(g, iset, oset, pplane, &forced))
+}
+
+/// Finds a maximally-delayed Pauli flow and returns the correction
+/// function, layer vector, sorted layer buckets, per-node branch, and
+/// depth in one call.
+#[pyfunction]
+#[allow(clippy::type_complexity)]
+fn find_pflow_structured(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+) -> PyResult<
+    Option<(
+        HashMap<usize, Nodes>,
+        Layer,
+        Vec<Vec<usize>>,
+        HashMap<usize, u8>,
+        usize,
+    )>,
+> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    Ok(pflow::find_structured(g, iset, oset, pplane).map(|r| {
+        let branch = r.branch.iter().map(|(&u, &b)| (u, branch_to_u8(b))).collect();
+        (r.f, r.layer, r.buckets, branch, r.depth)
+    }))
+}
+
+/// Validates a candidate causal flow against the definition.
+#[pyfunction]
+fn verify_flow(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    f: HashMap<usize, usize>,
+    layer: Layer,
+) -> PyResult<()> {
+    flow::verify(&g, &iset, &oset, &f, &layer).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Validates a candidate gflow against the definition.
+#[pyfunction]
+fn verify_gflow(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    plane: HashMap<usize, PlaneLike>,
+    f: HashMap<usize, Nodes>,
+    layer: Layer,
+) -> PyResult<()> {
+    let plane: HashMap<_, _> = plane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    gflow::verify(&g, &iset, &oset, &plane, &f, &layer)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Validates a candidate Pauli flow against the definition.
+#[pyfunction]
+fn verify_pflow(
+    g: Vec<Nodes>,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlaneLike>,
+    f: HashMap<usize, Nodes>,
+    layer: Layer,
+) -> PyResult<()> {
+    let pplane: HashMap<_, _> = pplane.into_iter().map(|(u, p)| (u, p.0)).collect();
+    pflow::verify(&g, &iset, &oset, &pplane, &f, &layer)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+fn fastflow(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Plane>()?;
+    m.add_class::<PPlane>()?;
+    m.add("EmptyGraphError", m.py().get_type::<EmptyGraphError>())?;
+    m.add("NoFlowError", m.py().get_type::<NoFlowError>())?;
+    m.add("DepthExceededError", m.py().get_type::<DepthExceededError>())?;
+    m.add_function(wrap_pyfunction!(absolute_schedule, m)?)?;
+    m.add_function(wrap_pyfunction!(adjacency_bitsets, m)?)?;
+    m.add_function(wrap_pyfunction!(check_domain, m)?)?;
+    m.add_function(wrap_pyfunction!(check_initial, m)?)?;
+    m.add_function(wrap_pyfunction!(complement, m)?)?;
+    m.add_function(wrap_pyfunction!(correction_fan_in, m)?)?;
+    m.add_function(wrap_pyfunction!(cycle_rank, m)?)?;
+    m.add_function(wrap_pyfunction!(find_flow, m)?)?;
+    m.add_function(wrap_pyfunction!(find_flow_masked, m)?)?;
+    m.add_function(wrap_pyfunction!(flow_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(gf2_solve, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_from_edges, m)?)?;
+    m.add_function(wrap_pyfunction!(flow_to_csr, m)?)?;
+    m.add_function(wrap_pyfunction!(from_networkx, m)?)?;
+    m.add_function(wrap_pyfunction!(layer_to_order, m)?)?;
+    m.add_function(wrap_pyfunction!(layer_widths, m)?)?;
+    m.add_function(wrap_pyfunction!(max_width, m)?)?;
+    m.add_function(wrap_pyfunction!(neighborhood_symdiff, m)?)?;
+    m.add_function(wrap_pyfunction!(odd_neighbors, m)?)?;
+    m.add_function(wrap_pyfunction!(partial_order_to_networkx, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(sorted_corrections, m)?)?;
+    m.add_function(wrap_pyfunction!(find_gflow, m)?)?;
+    m.add_function(wrap_pyfunction!(find_gflow_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(find_gflow_with_reason, m)?)?;
+    m.add_function(wrap_pyfunction!(find_gflow_from_adjacency, m)?)?;
+    m.add_function(wrap_pyfunction!(find_gflow_masked, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_from_adjacency, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_masked, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_structured, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_with_branches, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_with_progress, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pflow_with_timeout, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_flow, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_gflow, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_pflow, m)?)?;
+    Ok(())
+}
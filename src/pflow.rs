@@ -0,0 +1,1703 @@
+//! Maximally-delayed Pauli flow.
+
+use std::collections::HashMap;
+
+use fixedbitset::FixedBitSet;
+
+use anyhow::ensure;
+use rayon::prelude::*;
+
+use crate::common::{
+    check_domain, check_graph, check_initial, check_no_input_in_corrections, odd_neighbors,
+    Graph, Layer, Nodes,
+};
+use crate::gf2_linalg::GF2Solver;
+
+/// Measurement description of a node: either a plane or a Pauli axis.
+///
+/// Exported to Python as an enum; the variant values coincide with the
+/// wire-format integers, so `PPlane.X == 3` holds.
+#[pyo3::pyclass(eq, eq_int)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PPlane {
+    XY,
+    YZ,
+    XZ,
+    X,
+    Y,
+    Z,
+}
+
+impl PPlane {
+    /// Whether this is a Pauli measurement.
+    pub fn is_pauli(self) -> bool {
+        matches!(self, Self::X | Self::Y | Self::Z)
+    }
+
+    /// A measurement plane containing this measurement, for running the
+    /// plane-only finders on a Pauli-annotated graph. Pauli axes lie in
+    /// two planes (three for Y); the first in XY, XZ, YZ order is used.
+    pub fn plane(self) -> crate::gflow::Plane {
+        match self {
+            Self::XY | Self::X | Self::Y => crate::gflow::Plane::XY,
+            Self::XZ | Self::Z => crate::gflow::Plane::XZ,
+            Self::YZ => crate::gflow::Plane::YZ,
+        }
+    }
+}
+
+/// Reinterprets a gflow result as a Pauli flow.
+///
+/// A gflow is exactly a Pauli flow in which every node carries a plane
+/// (never an axis) measurement, so the correction function and
+/// layering carry over verbatim; only the measurement labels change
+/// representation. The converted flow is checked with [`verify`]
+/// before being returned, so a malformed input surfaces here instead
+/// of deep inside pflow-based extraction code.
+pub fn from_gflow(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    plane: &HashMap<usize, crate::gflow::Plane>,
+    f: &crate::gflow::GFlow,
+    layer: &Layer,
+) -> anyhow::Result<(PFlow, HashMap<usize, PPlane>)> {
+    use crate::gflow::Plane;
+    let pplane: HashMap<usize, PPlane> = plane
+        .iter()
+        .map(|(&u, &p)| {
+            let pp = match p {
+                Plane::XY => PPlane::XY,
+                Plane::YZ => PPlane::YZ,
+                Plane::XZ => PPlane::XZ,
+            };
+            (u, pp)
+        })
+        .collect();
+    verify(g, iset, oset, &pplane, f, layer)?;
+    Ok((f.clone(), pplane))
+}
+
+/// Upgrades plane measurements to Pauli axes from their angles.
+///
+/// An angle within `atol` of a multiple of `π/2` pins the measurement
+/// to an axis of its plane: even multiples select the plane's first
+/// axis (X for XY and XZ, Y for YZ), odd multiples the second (Y for
+/// XY, Z for YZ and XZ). Other angles keep the plain plane. This is
+/// the usual preprocessing in front of [`find`]; fails if `angles`
+/// misses a measured node.
+pub fn pplane_from_angles(
+    plane: HashMap<usize, crate::gflow::Plane>,
+    angles: &HashMap<usize, f64>,
+    atol: f64,
+) -> anyhow::Result<HashMap<usize, PPlane>> {
+    use crate::gflow::Plane;
+    plane
+        .into_iter()
+        .map(|(u, p)| {
+            let Some(&angle) = angles.get(&u) else {
+                anyhow::bail!("no angle for measured node: {u}");
+            };
+            let half_turns = (angle / std::f64::consts::FRAC_PI_2).round();
+            let pp = if (angle - half_turns * std::f64::consts::FRAC_PI_2).abs() > atol {
+                match p {
+                    Plane::XY => PPlane::XY,
+                    Plane::YZ => PPlane::YZ,
+                    Plane::XZ => PPlane::XZ,
+                }
+            } else if half_turns.rem_euclid(2.0) < 0.5 {
+                match p {
+                    Plane::XY | Plane::XZ => PPlane::X,
+                    Plane::YZ => PPlane::Y,
+                }
+            } else {
+                match p {
+                    Plane::XY => PPlane::Y,
+                    Plane::YZ | Plane::XZ => PPlane::Z,
+                }
+            };
+            Ok((u, pp))
+        })
+        .collect()
+}
+
+/// Shape of a single correction-set ansatz tried for a node.
+///
+/// Planes admit exactly one branch while Pauli measurements admit the
+/// branches of every plane containing their axis.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Branch {
+    /// `u` not in the correction set, `u` in its odd neighborhood.
+    XY,
+    /// `u` in the correction set and in its odd neighborhood.
+    XZ,
+    /// `u` in the correction set, not in its odd neighborhood.
+    YZ,
+}
+
+impl Branch {
+    /// Branches tried for a node, in order.
+    fn candidates(pplane: PPlane) -> &'static [Self] {
+        match pplane {
+            PPlane::XY => &[Self::XY],
+            PPlane::XZ => &[Self::XZ],
+            PPlane::YZ => &[Self::YZ],
+            PPlane::X => &[Self::XY, Self::XZ],
+            PPlane::Y => &[Self::XY, Self::XZ, Self::YZ],
+            PPlane::Z => &[Self::XZ, Self::YZ],
+        }
+    }
+
+    /// Whether this branch places the node in its own correction set.
+    ///
+    /// `true` for XZ and YZ: their correction sets always contain the
+    /// node itself, so callers must not assume `u ∉ f(u)`. `false` for
+    /// XY: the solver's column basis excludes the node, so an XY
+    /// correction set can never contain it, not even spuriously.
+    pub fn contains_self(self) -> bool {
+        !matches!(self, Self::XY)
+    }
+}
+
+/// Correction function of a Pauli flow: maps each measured node to its
+/// correction set.
+pub type PFlow = HashMap<usize, Nodes>;
+
+/// Accounting of the solve attempts of one branch shape.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BranchStats {
+    /// Number of candidate systems solved.
+    pub attempts: u64,
+    /// Number of attempts that produced a correction set.
+    pub successes: u64,
+    /// Cumulative wall-clock time spent in the attempts.
+    pub duration: std::time::Duration,
+}
+
+/// Shape and branch outcomes of one candidate solve, collected by
+/// [`find_with_node_stats`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NodeStats {
+    /// Round in which the candidate was tried.
+    pub round: usize,
+    /// Rows of the candidate's system, i.e. odd-neighborhood
+    /// conditions.
+    pub rows: usize,
+    /// Columns of the candidate's system, i.e. nodes allowed in the
+    /// correction set.
+    pub cols: usize,
+    /// Branches tried, in candidate order, with whether each produced
+    /// a correction set.
+    pub attempted: Vec<(Branch, bool)>,
+}
+
+/// Finds a maximally-delayed Pauli flow, if one exists.
+///
+/// `pplane` must assign a measurement plane or Pauli axis to each
+/// non-output node. Returns the correction function together with the
+/// layer of each node, outputs being layer `0`. The result is
+/// deterministic: the solver's column basis is in increasing node order
+/// and the returned correction set is the unique solution with all free
+/// variables zero.
+///
+/// A Pauli flow requires `|iset| <= |oset|`; when there are more
+/// inputs than outputs the search returns `None` immediately instead
+/// of grinding through rounds that cannot succeed.
+///
+/// Unlike a causal flow, a correction set `f(u)` may contain `u`
+/// itself: this happens exactly when the node is solved in the XZ or
+/// YZ branch (see [`Branch::contains_self`]), which
+/// [`find_with_branch_report`] exposes. XY-solved nodes never appear
+/// in their own correction set.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<(PFlow, Layer)> {
+    find_with_branches(g, iset, oset, pplane, &HashMap::new())
+}
+
+/// Finds the depth of the maximally-delayed Pauli flow, if one exists.
+///
+/// The depth is the number of the deepest layer, i.e. `0` when every
+/// node is an output.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_depth(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<usize> {
+    let (_, layer) = find(g, iset, oset, pplane)?;
+    Some(layer.iter().copied().max().unwrap_or(0))
+}
+
+/// Finds a maximally-delayed Pauli flow of depth at most `max_depth`.
+///
+/// Equivalent to [`find`], except the search gives up and returns
+/// `None` as soon as a round beyond the bound would be needed, instead
+/// of computing the deeper layers only to have the caller reject them.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_max_depth(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    max_depth: usize,
+) -> Option<(PFlow, Layer)> {
+    let (f, layer, _, _, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        Some(max_depth),
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer))
+}
+
+/// Cooperative interruption of a search.
+///
+/// Checked at the top of each round and before each candidate solve: a
+/// search stops once the deadline has passed or the cancel flag is
+/// set. The default never triggers.
+#[derive(Clone, Copy, Default)]
+pub struct Interrupt<'a> {
+    /// Wall-clock instant after which the search gives up.
+    pub deadline: Option<std::time::Instant>,
+    /// External cancellation flag, set from another thread.
+    pub cancel: Option<&'a std::sync::atomic::AtomicBool>,
+}
+
+impl Interrupt<'_> {
+    /// Whether the search should stop now.
+    fn triggered(&self) -> bool {
+        self.deadline
+            .is_some_and(|d| std::time::Instant::now() >= d)
+            || self
+                .cancel
+                .is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// Marker error of a search stopped by its [`Interrupt`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Interrupted;
+
+/// Finds a maximally-delayed Pauli flow under a deadline or an
+/// external cancellation flag.
+///
+/// Returns `Err(Interrupted)` when the interrupt fires before the
+/// search finishes, distinguishing an aborted search from the absence
+/// of a flow.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_interrupt(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    interrupt: Interrupt<'_>,
+) -> Result<Option<(PFlow, Layer)>, Interrupted> {
+    let result = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        interrupt,
+        None,
+        false,
+    )?;
+    Ok(result.map(|(f, layer, _, _, _, _)| (f, layer)))
+}
+
+/// Finds a maximally-delayed Pauli flow, reporting progress after each
+/// completed layer.
+///
+/// `on_layer` receives the layer number just finished, the number of
+/// nodes corrected in it, and the number of nodes still uncorrected.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_progress(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    on_layer: &mut dyn FnMut(usize, usize, usize),
+) -> Option<(PFlow, Layer)> {
+    let (f, layer, _, _, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        Some(on_layer),
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer))
+}
+
+/// Finds a maximally-delayed Pauli flow, also reporting which branch
+/// produced each node's correction set.
+///
+/// The branch determines the correction basis during circuit
+/// extraction; it is computed by the search anyway and returned here
+/// instead of being discarded.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_branch_report(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<(PFlow, Layer, HashMap<usize, Branch>)> {
+    let (f, layer, branch, _, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer, branch))
+}
+
+/// Finds a maximally-delayed Pauli flow with the branch of some nodes
+/// forced.
+///
+/// Nodes in `forced` only ever try the given branch; if it is not
+/// admitted by their pplane or has no solution, no flow is returned.
+/// Other nodes behave as in [`find`].
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_branches(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    forced: &HashMap<usize, Branch>,
+) -> Option<(PFlow, Layer)> {
+    let (f, layer, _, _, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        forced,
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer))
+}
+
+/// Finds a maximally-delayed Pauli flow with a preferred branch order
+/// per node.
+///
+/// Nodes in `preference` try their listed branches first, in the given
+/// order, so a preferred branch wins whenever it is solvable even if
+/// the default order would pick another. Entries not admitted by the
+/// node's pplane are skipped and admitted branches missing from the
+/// list are appended in the default order, so a preference only ever
+/// reorders the tries — it never changes which nodes are solvable or
+/// the layering. Other nodes behave as in [`find`]. The branch that
+/// won is reported per node, as in [`find_with_branch_report`].
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_branch_preference(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    preference: &HashMap<usize, Vec<Branch>>,
+) -> Option<(PFlow, Layer, HashMap<usize, Branch>)> {
+    let (f, layer, branch, _, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        preference,
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer, branch))
+}
+
+/// Checks a candidate Pauli flow against the definition.
+///
+/// Validates the graph and domain invariants, the layering, and the
+/// Pauli flow conditions: each node's plane or axis condition on its
+/// correction set, the ordering of correction sets and their odd
+/// neighborhoods (relaxed for Pauli-measured nodes), and the Pauli-Y
+/// coupling between unordered pairs. Errors name the offending node
+/// and the violated condition. Intended for flows produced outside the
+/// finders; the finders' own results always pass.
+pub fn verify(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    pplane: &HashMap<usize, PPlane>,
+    f: &PFlow,
+    layer: &Layer,
+) -> anyhow::Result<()> {
+    check_graph(g, iset, oset)?;
+    let n = g.len();
+    let vset: Nodes = (0..n).collect();
+    check_domain(pplane, &vset, oset)?;
+    check_domain(f, &vset, oset)?;
+    ensure!(layer.len() == n, "layer length mismatch");
+    check_initial(layer, oset)?;
+    check_no_input_in_corrections(f, iset)?;
+    // `w` is measured strictly after `u`.
+    let after = |u: usize, w: usize| layer[w] < layer[u];
+    for (&u, fu) in f {
+        ensure!(layer[u] > 0, "measured node in layer 0: {u}");
+        let odd = odd_neighbors(g, fu);
+        let ok = match pplane[&u] {
+            PPlane::XY => !fu.contains(&u) && odd.contains(&u),
+            PPlane::XZ => fu.contains(&u) && odd.contains(&u),
+            PPlane::YZ => fu.contains(&u) && !odd.contains(&u),
+            PPlane::X => odd.contains(&u),
+            PPlane::Z => fu.contains(&u),
+            PPlane::Y => fu.contains(&u) || odd.contains(&u),
+        };
+        ensure!(ok, "plane condition violated: {u}");
+        for &w in fu {
+            ensure!(
+                w == u
+                    || after(u, w)
+                    || matches!(pplane.get(&w), Some(PPlane::X | PPlane::Y)),
+                "correction set of {u} not ordered after it: {w}"
+            );
+        }
+        for &w in &odd {
+            ensure!(
+                w == u
+                    || after(u, w)
+                    || matches!(pplane.get(&w), Some(PPlane::Y | PPlane::Z)),
+                "odd neighborhood of {u} not ordered after it: {w}"
+            );
+        }
+        for (&w, &pw) in pplane {
+            if w != u && pw == PPlane::Y && !after(u, w) {
+                ensure!(
+                    fu.contains(&w) == odd.contains(&w),
+                    "Pauli-Y condition violated between {u} and {w}"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finds a maximally-delayed Pauli flow whose correction sets draw from
+/// at most `corrector_budget` distinct nodes.
+///
+/// An unrestricted search is run first and returned as-is if it already
+/// fits the budget. Otherwise the most frequently used correctors are
+/// kept, the others are barred from correction sets, and the search is
+/// repeated; this is a heuristic and may miss budget-conforming flows
+/// that use a different corrector selection.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_budget(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    corrector_budget: usize,
+) -> Option<(PFlow, Layer)> {
+    let used = |f: &PFlow| -> Nodes { f.values().flatten().copied().collect() };
+    let (f, layer, _, _, _, _) = find_core(
+        g.clone(),
+        iset.clone(),
+        oset.clone(),
+        pplane.clone(),
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    let correctors = used(&f);
+    if correctors.len() <= corrector_budget {
+        return Some((f, layer));
+    }
+    // Keep the busiest correctors, breaking ties by node index.
+    let mut usage: HashMap<usize, usize> = HashMap::new();
+    for fu in f.values() {
+        for &v in fu {
+            *usage.entry(v).or_default() += 1;
+        }
+    }
+    let mut ranked: Vec<usize> = correctors.into_iter().collect();
+    ranked.sort_unstable_by_key(|&v| (std::cmp::Reverse(usage[&v]), v));
+    let allowed: Nodes = ranked.into_iter().take(corrector_budget).collect();
+    let (f, layer, _, _, _, _) =
+        find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        Some(&allowed),
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    // Self-corrections of XZ/YZ branches bypass the restriction, so the
+    // budget must be re-checked on the result.
+    (used(&f).len() <= corrector_budget).then_some((f, layer))
+}
+
+/// Finds a maximally-delayed Pauli flow, reporting the nullity of each
+/// node's solution space alongside the result.
+///
+/// The nullity is the log2 number of correction sets solving the
+/// node's branch in its round: zero means the correction was forced,
+/// larger values indicate slack.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_nullity(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<(PFlow, Layer, HashMap<usize, u32>)> {
+    let (f, layer, _, _, nullity, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer, nullity))
+}
+
+/// Finds a maximally-delayed Pauli flow, reporting per-branch solve
+/// statistics alongside the result.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_stats(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<(PFlow, Layer, HashMap<Branch, BranchStats>)> {
+    let (f, layer, _, stats, _, _) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer, stats))
+}
+
+/// Finds a maximally-delayed Pauli flow, recording the shape of every
+/// candidate solve and the branches it attempted.
+///
+/// Each measured node gets one [`NodeStats`] record per round in which
+/// it was a candidate; a node failing several rounds before being
+/// corrected accumulates several records. The records point at the
+/// dense sub-instances driving runtime. [`find`] itself skips the
+/// bookkeeping entirely.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_with_node_stats(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<(PFlow, Layer, HashMap<usize, Vec<NodeStats>>)> {
+    let (f, layer, _, _, _, node_stats) = find_core(
+        g,
+        iset,
+        oset,
+        pplane,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        true,
+    )
+    .expect("no interrupt configured")?;
+    Some((f, layer, node_stats))
+}
+
+/// Runs the per-node solve of [`find`] once, for a single target.
+///
+/// `solved` lists the measured nodes treated as already corrected, as
+/// if the search had processed them in earlier rounds; the candidate
+/// system of `u` is then built exactly as [`find`] would build it.
+/// Returns the branch that succeeded, the correction set, and the
+/// solution-space nullity, or `None` when no branch admits a
+/// correction in this context. This is the per-node kernel of the
+/// search loop exposed directly, for what-if analysis and for stepping
+/// through the algorithm by hand.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails, or if `u` is not
+/// an uncorrected measured node.
+pub fn solve_single(
+    g: &Graph,
+    iset: &Nodes,
+    oset: &Nodes,
+    pplane: &HashMap<usize, PPlane>,
+    u: usize,
+    solved: &Nodes,
+) -> Option<(Branch, Nodes, u32)> {
+    check_graph(g, iset, oset).expect("graph is malformed");
+    let n = g.len();
+    let vset: Nodes = (0..n).collect();
+    check_domain(pplane, &vset, oset).expect("pplane domain is malformed");
+    let ocset: Nodes = vset
+        .difference(oset)
+        .filter(|v| !solved.contains(v))
+        .copied()
+        .collect();
+    assert!(
+        ocset.contains(&u),
+        "target must be an uncorrected measured node"
+    );
+    let col_base: Vec<usize> = (0..n)
+        .filter(|&v| {
+            !iset.contains(&v)
+                && (!ocset.contains(&v) || matches!(pplane[&v], PPlane::X | PPlane::Y))
+        })
+        .collect();
+    let mut row_base: Vec<usize> = ocset
+        .iter()
+        .filter(|&&w| pplane[&w] != PPlane::Z)
+        .copied()
+        .collect();
+    row_base.sort_unstable();
+    let (hit, _) = solve_candidate(
+        g,
+        iset,
+        pplane,
+        u,
+        Branch::candidates(pplane[&u]),
+        &col_base,
+        &row_base,
+    );
+    hit
+}
+
+/// Everything the shared search loop accumulates: the flow, the
+/// layers, the successful branch, per-branch solve statistics, the
+/// per-node solution-space nullity, and (when requested) the per-node
+/// solve records.
+type FindCoreResult = (
+    PFlow,
+    Layer,
+    HashMap<usize, Branch>,
+    HashMap<Branch, BranchStats>,
+    HashMap<usize, u32>,
+    HashMap<usize, Vec<NodeStats>>,
+);
+
+/// Shared search loop of the Pauli flow finders, additionally
+/// reporting the branch that succeeded for each node, per-branch
+/// solve statistics, and per-node nullity.
+#[allow(clippy::too_many_arguments)]
+fn find_core(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+    forced: &HashMap<usize, Branch>,
+    preference: &HashMap<usize, Vec<Branch>>,
+    allowed: Option<&Nodes>,
+    max_depth: Option<usize>,
+    interrupt: Interrupt<'_>,
+    mut progress: Option<&mut dyn FnMut(usize, usize, usize)>,
+    collect_stats: bool,
+) -> Result<Option<FindCoreResult>, Interrupted> {
+    check_graph(&g, &iset, &oset).expect("graph is malformed");
+    let n = g.len();
+    let vset: Nodes = (0..n).collect();
+    check_domain(&pplane, &vset, &oset).expect("pplane domain is malformed");
+    let mut f = PFlow::new();
+    let mut layer = vec![0; n];
+    let mut branches = HashMap::new();
+    let mut nullity = HashMap::new();
+    let mut stats: HashMap<Branch, BranchStats> = HashMap::new();
+    let mut node_stats: HashMap<usize, Vec<NodeStats>> = HashMap::new();
+    check_initial(&layer, &oset).expect("initial layer is malformed");
+    // Nodes not yet corrected.
+    let mut ocset: Nodes = vset.difference(&oset).copied().collect();
+    // Every node is an output (or the graph is empty): the empty flow
+    // of depth 0 stands without entering the round machinery.
+    if ocset.is_empty() {
+        return Ok(Some((f, layer, branches, stats, nullity, node_stats)));
+    }
+    // More inputs than outputs: no Pauli flow exists, so reject up
+    // front instead of stalling after some rounds.
+    if iset.len() > oset.len() {
+        log::debug!(
+            "pflow: {} inputs exceed {} outputs",
+            iset.len(),
+            oset.len()
+        );
+        return Ok(None);
+    }
+    // An isolated measured node can only be corrected through the YZ
+    // branch (`f(u) = {u}` with an empty odd neighborhood); a pplane
+    // that does not admit it can never put `u` in its own odd
+    // neighborhood, so fail fast instead of grinding rounds.
+    if let Some(u) = ocset
+        .iter()
+        .find(|&&u| g[u].is_empty() && !Branch::candidates(pplane[&u]).contains(&Branch::YZ))
+    {
+        log::debug!("pflow: isolated measured node cannot be corrected: {u}");
+        return Ok(None);
+    }
+    for k in 1.. {
+        if ocset.is_empty() {
+            break;
+        }
+        // Nodes remain past the depth cap: no flow of that depth.
+        if max_depth.is_some_and(|d| k > d) {
+            return Ok(None);
+        }
+        if interrupt.triggered() {
+            return Err(Interrupted);
+        }
+        // The candidate solves of one round are independent: each only
+        // reads the shared graph and writes its own scratch, so they
+        // run on the rayon pool and are merged afterwards.
+        let candidates: Vec<usize> = ocset.iter().copied().collect();
+        // The column and row pools depend only on the round, not on the
+        // candidate: each candidate merely excludes itself. Build them
+        // once here instead of rescanning the graph per candidate.
+        let col_base: Vec<usize> = (0..n)
+            .filter(|&v| {
+                !iset.contains(&v)
+                    && allowed.is_none_or(|a| a.contains(&v))
+                    && (!ocset.contains(&v) || matches!(pplane[&v], PPlane::X | PPlane::Y))
+            })
+            .collect();
+        let mut row_base: Vec<usize> = ocset
+            .iter()
+            .filter(|&&w| pplane[&w] != PPlane::Z)
+            .copied()
+            .collect();
+        // Sorted rows keep the work buffers, and thus the reductions,
+        // bit-identical across runs; `col_base` is already in node order.
+        row_base.sort_unstable();
+        log::debug!(
+            "pflow round {k}: {} candidates, {} columns",
+            candidates.len(),
+            col_base.len()
+        );
+        let solutions: Vec<_> = candidates
+            .par_iter()
+            .map(|&u| {
+                // A triggered interrupt skips the remaining solves; the
+                // round is abandoned right after the merge.
+                if interrupt.triggered() {
+                    return (u, None, Vec::new());
+                }
+                let admitted = Branch::candidates(pplane[&u]);
+                // Preferred branches first, then the remaining admitted
+                // ones in default order; inadmissible entries are
+                // skipped, so a preference can only reorder the tries,
+                // never change which nodes are solvable.
+                let mut order: Vec<Branch> = Vec::with_capacity(admitted.len());
+                for &b in preference.get(&u).map_or(&[][..], Vec::as_slice) {
+                    if admitted.contains(&b) && !order.contains(&b) {
+                        order.push(b);
+                    }
+                }
+                for &b in admitted {
+                    if !order.contains(&b) {
+                        order.push(b);
+                    }
+                }
+                let branches: Vec<Branch> = order
+                    .into_iter()
+                    .filter(|&b| forced.get(&u).is_none_or(|&fb| fb == b))
+                    .collect();
+                let (hit, attempts) =
+                    solve_candidate(&g, &iset, &pplane, u, &branches, &col_base, &row_base);
+                (u, hit, attempts)
+            })
+            .collect();
+        let mut corrected = Vec::new();
+        for (u, hit, attempts) in solutions {
+            if collect_stats {
+                // The dimensions are those of the system solve_candidate
+                // builds: the candidate excludes itself from the pools
+                // and heads the row list.
+                node_stats.entry(u).or_default().push(NodeStats {
+                    round: k,
+                    rows: 1 + row_base.iter().filter(|&&w| w != u).count(),
+                    cols: col_base.iter().filter(|&&v| v != u).count(),
+                    attempted: attempts.iter().map(|&(b, s)| (b, s.successes > 0)).collect(),
+                });
+            }
+            for (branch, delta) in attempts {
+                let entry = stats.entry(branch).or_default();
+                entry.attempts += delta.attempts;
+                entry.successes += delta.successes;
+                entry.duration += delta.duration;
+            }
+            if let Some((branch, fu, dim)) = hit {
+                f.insert(u, fu);
+                layer[u] = k;
+                branches.insert(u, branch);
+                nullity.insert(u, dim);
+                corrected.push(u);
+            }
+        }
+        if interrupt.triggered() {
+            return Err(Interrupted);
+        }
+        if corrected.is_empty() {
+            log::debug!("pflow round {k}: stalled with {} unsolved", ocset.len());
+            return Ok(None);
+        }
+        let solved = corrected.len();
+        log::debug!("pflow round {k}: corrected {solved}");
+        for u in corrected {
+            ocset.remove(&u);
+        }
+        if let Some(report) = progress.as_deref_mut() {
+            report(k, solved, ocset.len());
+        }
+    }
+    Ok(Some((f, layer, branches, stats, nullity, node_stats)))
+}
+
+/// Fully assembled result of a Pauli flow search, built Rust-side to
+/// spare callers per-field post-processing.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug)]
+pub struct StructuredFlow {
+    /// Correction function.
+    pub f: PFlow,
+    /// Per-node layer.
+    pub layer: Layer,
+    /// Nodes of each layer, sorted, indexed by layer.
+    pub buckets: Vec<Vec<usize>>,
+    /// Measurement description of each measured node.
+    pub pplane: HashMap<usize, PPlane>,
+    /// Branch that succeeded for each measured node.
+    pub branch: HashMap<usize, Branch>,
+    /// Number of the deepest layer.
+    pub depth: usize,
+}
+
+/// Finds a maximally-delayed Pauli flow and assembles all derived data
+/// in one pass.
+///
+/// # Panics
+///
+/// Panics if `check_graph` or `check_domain` fails.
+pub fn find_structured(
+    g: Graph,
+    iset: Nodes,
+    oset: Nodes,
+    pplane: HashMap<usize, PPlane>,
+) -> Option<StructuredFlow> {
+    let (f, layer, branch, _, _, _) =
+        find_core(
+        g,
+        iset,
+        oset,
+        pplane.clone(),
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        Interrupt::default(),
+        None,
+        false,
+    )
+    .expect("no interrupt configured")?;
+    let depth = layer.iter().copied().max().unwrap_or(0);
+    let mut buckets = vec![Vec::new(); depth + 1];
+    for (u, &k) in layer.iter().enumerate() {
+        buckets[k].push(u);
+    }
+    for bucket in &mut buckets {
+        bucket.sort_unstable();
+    }
+    Some(StructuredFlow {
+        f,
+        layer,
+        buckets,
+        pplane,
+        branch,
+        depth,
+    })
+}
+
+/// Reusable Pauli flow finder that keeps its buffers across calls.
+///
+/// Sweeping many graphs through [`find`] churns the allocator: every
+/// round of every call builds fresh work matrices. `FlowFinder` owns
+/// the scratch instead and reuses any pooled row whose width matches
+/// the next system, only reallocating when the shape actually changes
+/// — on similarly sized graphs almost never. Candidates are solved
+/// sequentially on the calling thread (the parallel [`find`] hands
+/// each candidate its own scratch instead); the result is identical.
+#[derive(Default)]
+pub struct FlowFinder {
+    rows: Vec<FixedBitSet>,
+    out: FixedBitSet,
+}
+
+impl FlowFinder {
+    /// Creates a finder with no buffers allocated yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finds a maximally-delayed Pauli flow; see [`find`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `check_graph` or `check_domain` fails.
+    pub fn find(
+        &mut self,
+        g: Graph,
+        iset: Nodes,
+        oset: Nodes,
+        pplane: HashMap<usize, PPlane>,
+    ) -> Option<(PFlow, Layer)> {
+        check_graph(&g, &iset, &oset).expect("graph is malformed");
+        let n = g.len();
+        let vset: Nodes = (0..n).collect();
+        check_domain(&pplane, &vset, &oset).expect("pplane domain is malformed");
+        let mut f = PFlow::new();
+        let mut layer = vec![0; n];
+        let mut ocset: Nodes = vset.difference(&oset).copied().collect();
+        for k in 1.. {
+            if ocset.is_empty() {
+                break;
+            }
+            let candidates: Vec<usize> = ocset.iter().copied().collect();
+            let col_base: Vec<usize> = (0..n)
+                .filter(|&v| {
+                    !iset.contains(&v)
+                        && (!ocset.contains(&v) || matches!(pplane[&v], PPlane::X | PPlane::Y))
+                })
+                .collect();
+            let mut row_base: Vec<usize> = ocset
+                .iter()
+                .filter(|&&w| pplane[&w] != PPlane::Z)
+                .copied()
+                .collect();
+            row_base.sort_unstable();
+            let mut corrected = Vec::new();
+            for &u in &candidates {
+                let branches = Branch::candidates(pplane[&u]);
+                if let Some(fu) =
+                    self.solve_candidate(&g, &iset, &pplane, u, branches, &col_base, &row_base)
+                {
+                    f.insert(u, fu);
+                    layer[u] = k;
+                    corrected.push(u);
+                }
+            }
+            if corrected.is_empty() {
+                return None;
+            }
+            for u in corrected {
+                ocset.remove(&u);
+            }
+        }
+        Some((f, layer))
+    }
+
+    /// Mirrors the free-function candidate solve, filling rows taken
+    /// from the internal pool and returning them to it afterwards.
+    #[allow(clippy::too_many_arguments)]
+    fn solve_candidate(
+        &mut self,
+        g: &Graph,
+        iset: &Nodes,
+        pplane: &HashMap<usize, PPlane>,
+        u: usize,
+        branches: &[Branch],
+        col_base: &[usize],
+        row_base: &[usize],
+    ) -> Option<Nodes> {
+        let colset: Vec<usize> = col_base.iter().filter(|&&v| v != u).copied().collect();
+        let feasible: Vec<Branch> = branches
+            .iter()
+            .filter(|&&b| b == Branch::XY || !iset.contains(&u))
+            .copied()
+            .collect();
+        if colset.is_empty() || feasible.is_empty() {
+            return None;
+        }
+        let rowset: Vec<usize> = std::iter::once(u)
+            .chain(row_base.iter().filter(|&&w| w != u).copied())
+            .collect();
+        let width = colset.len() + feasible.len();
+        self.rows.resize_with(rowset.len(), FixedBitSet::new);
+        for (r, &w) in rowset.iter().enumerate() {
+            let row = &mut self.rows[r];
+            if row.len() == width {
+                row.clear();
+            } else {
+                *row = FixedBitSet::with_capacity(width);
+            }
+            let is_y = w != u && pplane[&w] == PPlane::Y;
+            for (c, &v) in colset.iter().enumerate() {
+                if g[w].contains(&v) != (is_y && v == w) {
+                    row.insert(c);
+                }
+            }
+            for (ieq, &branch) in feasible.iter().enumerate() {
+                let mut bit = w == u && branch != Branch::YZ;
+                if branch != Branch::XY && g[w].contains(&u) {
+                    bit = !bit;
+                }
+                row.set(colset.len() + ieq, bit);
+            }
+        }
+        let mut solver = GF2Solver::attach(std::mem::take(&mut self.rows), feasible.len());
+        self.out.grow(colset.len());
+        let mut hit = None;
+        for (ieq, &branch) in feasible.iter().enumerate() {
+            if solver.solve_in_place(&mut self.out, ieq) {
+                let mut fu: Nodes = self.out.ones().map(|c| colset[c]).collect();
+                if branch != Branch::XY {
+                    fu.insert(u);
+                }
+                hit = Some(fu);
+                break;
+            }
+        }
+        self.rows = solver.detach();
+        hit
+    }
+}
+
+/// Tries the candidate branches of `u` in one combined solve.
+///
+/// `col_base` and `row_base` are the round-wide column and row pools
+/// computed in `find_core`; the candidate only excludes itself from
+/// them (and heads the row list).
+///
+/// All branches share the coefficient matrix — only the right-hand side
+/// differs — so it is reduced once with one right-hand side column per
+/// branch and each branch is decoded from its own column. The first
+/// branch, in candidate order, whose column is consistent wins. Per
+/// branch, the returned stats record one attempt; the shared reduction
+/// cost lands on the branch tried first.
+#[allow(clippy::type_complexity)]
+fn solve_candidate(
+    g: &Graph,
+    iset: &Nodes,
+    pplane: &HashMap<usize, PPlane>,
+    u: usize,
+    branches: &[Branch],
+    col_base: &[usize],
+    row_base: &[usize],
+) -> (Option<(Branch, Nodes, u32)>, Vec<(Branch, BranchStats)>) {
+    let mut attempts = Vec::new();
+    // Columns: nodes allowed in the correction set besides `u` itself,
+    // i.e. non-input processed nodes and unprocessed Pauli-X/Y nodes.
+    let colset: Vec<usize> = col_base.iter().filter(|&&v| v != u).copied().collect();
+    // An input node cannot appear in its own correction set, so the
+    // XZ/YZ branches fail without solving.
+    let feasible: Vec<Branch> = branches
+        .iter()
+        .filter(|&&b| {
+            let ok = b == Branch::XY || !iset.contains(&u);
+            if !ok {
+                attempts.push((
+                    b,
+                    BranchStats {
+                        attempts: 1,
+                        ..BranchStats::default()
+                    },
+                ));
+            }
+            ok
+        })
+        .copied()
+        .collect();
+    if colset.is_empty() || feasible.is_empty() {
+        for &b in &feasible {
+            attempts.push((
+                b,
+                BranchStats {
+                    attempts: 1,
+                    ..BranchStats::default()
+                },
+            ));
+        }
+        return (None, attempts);
+    }
+    // Rows: the odd-neighborhood conditions on `u` and the unprocessed
+    // nodes. Pauli-Z nodes are unconstrained; Pauli-Y nodes must appear
+    // in the correction set and its odd neighborhood simultaneously.
+    let rowset: Vec<usize> = std::iter::once(u)
+        .chain(row_base.iter().filter(|&&w| w != u).copied())
+        .collect();
+    let width = colset.len() + feasible.len();
+    let mut work = vec![FixedBitSet::with_capacity(width); rowset.len()];
+    for (r, &w) in rowset.iter().enumerate() {
+        let is_y = w != u && pplane[&w] == PPlane::Y;
+        for (c, &v) in colset.iter().enumerate() {
+            if g[w].contains(&v) != (is_y && v == w) {
+                work[r].insert(c);
+            }
+        }
+        for (ieq, &branch) in feasible.iter().enumerate() {
+            let mut bit = w == u && branch != Branch::YZ;
+            if branch != Branch::XY && g[w].contains(&u) {
+                bit = !bit;
+            }
+            work[r].set(colset.len() + ieq, bit);
+        }
+    }
+    let mut solver = GF2Solver::attach(work, feasible.len());
+    let mut out = FixedBitSet::with_capacity(colset.len());
+    for (ieq, &branch) in feasible.iter().enumerate() {
+        let start = std::time::Instant::now();
+        let solved = solver.solve_in_place(&mut out, ieq);
+        attempts.push((
+            branch,
+            BranchStats {
+                attempts: 1,
+                successes: u64::from(solved),
+                duration: start.elapsed(),
+            },
+        ));
+        if solved {
+            let nullity = (colset.len() - solver.rank()) as u32;
+            let mut fu: Nodes = out.ones().map(|c| colset[c]).collect();
+            if branch != Branch::XY {
+                fu.insert(u);
+            }
+            return (Some((branch, fu, nullity)), attempts);
+        }
+    }
+    (None, attempts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, nodeset, pplanes};
+
+    #[test]
+    fn test_find_trivial() {
+        // All nodes are outputs: the empty flow of depth 0.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let (f, layer) = find(g, nodeset([0]), nodeset([0, 1]), pplanes([])).unwrap();
+        assert!(f.is_empty());
+        assert_eq!(layer, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_from_gflow() {
+        use crate::gflow::{self, Plane};
+        // Node 0 corrects itself in the YZ plane.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let plane = HashMap::from([(0, Plane::YZ)]);
+        let (gf, layer) = gflow::find(g.clone(), nodeset([]), nodeset([1]), plane.clone()).unwrap();
+        let (f, pplane) = from_gflow(&g, &nodeset([]), &nodeset([1]), &plane, &gf, &layer).unwrap();
+        assert_eq!(f, gf);
+        assert_eq!(pplane, pplanes([(0, PPlane::YZ)]));
+        // A bogus layering is rejected by the embedded verifier.
+        assert!(from_gflow(&g, &nodeset([]), &nodeset([1]), &plane, &gf, &vec![0; 2]).is_err());
+    }
+
+    #[test]
+    fn test_flow_finder_matches_find() {
+        // One finder serves several searches; the buffers carry over
+        // between calls of different sizes.
+        let mut finder = FlowFinder::new();
+        let cases = [
+            (
+                3,
+                vec![(0, 1), (1, 2)],
+                nodeset([0]),
+                nodeset([2]),
+                pplanes([(0, PPlane::XY), (1, PPlane::XY)]),
+            ),
+            (
+                4,
+                vec![(0, 1), (0, 2), (1, 3)],
+                nodeset([]),
+                nodeset([1, 2]),
+                pplanes([(0, PPlane::X), (3, PPlane::YZ)]),
+            ),
+            (
+                3,
+                vec![(0, 1), (1, 2), (2, 0)],
+                nodeset([0]),
+                nodeset([2]),
+                pplanes([(0, PPlane::Y), (1, PPlane::Z)]),
+            ),
+        ];
+        for (n, edges, iset, oset, pplane) in cases {
+            let g = test_utils::graph(n, &edges);
+            assert_eq!(
+                finder.find(g.clone(), iset.clone(), oset.clone(), pplane.clone()),
+                find(g, iset, oset, pplane)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pplane_from_angles() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+
+        use crate::gflow::Plane;
+        let plane = HashMap::from([
+            (0, Plane::XY),
+            (1, Plane::XY),
+            (2, Plane::YZ),
+            (3, Plane::XZ),
+        ]);
+        let angles = HashMap::from([(0, PI), (1, FRAC_PI_2 + 1e-12), (2, 0.3), (3, -FRAC_PI_2)]);
+        let pplane = pplane_from_angles(plane, &angles, 1e-9).unwrap();
+        let expected = pplanes([
+            (0, PPlane::X),
+            (1, PPlane::Y),
+            (2, PPlane::YZ),
+            (3, PPlane::Z),
+        ]);
+        assert_eq!(pplane, expected);
+    }
+
+    #[test]
+    fn test_pplane_from_angles_missing() {
+        use crate::gflow::Plane;
+        let plane = HashMap::from([(0, Plane::XY)]);
+        let err = pplane_from_angles(plane, &HashMap::new(), 1e-9).unwrap_err();
+        assert!(err.to_string().contains("no angle for measured node: 0"));
+    }
+
+    #[test]
+    fn test_find_line() {
+        // 0 - 1 - 2
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let (f, layer) = find(g, nodeset([0]), nodeset([2]), pplane).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_find_depth() {
+        // The Pauli shortcut lets both nodes share one layer.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::X)]);
+        assert_eq!(find_depth(g, nodeset([0]), nodeset([2]), pplane), Some(1));
+    }
+
+    #[test]
+    fn test_find_with_max_depth() {
+        // The line needs two layers: a cap of 1 rejects it, a cap of 2
+        // reproduces the unrestricted flow.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        assert!(
+            find_with_max_depth(g.clone(), nodeset([0]), nodeset([2]), pplane.clone(), 1)
+                .is_none()
+        );
+        let (f, layer) =
+            find_with_max_depth(g, nodeset([0]), nodeset([2]), pplane, 2).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_find_pauli_shortcut() {
+        // Pauli-measured middle node lets both ends be measured first.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::X)]);
+        let (f, layer) = find(g, nodeset([0]), nodeset([2]), pplane).unwrap();
+        assert!(f[&0].contains(&1));
+        assert_eq!(layer[2], 0);
+        assert!(layer[0] <= 2 && layer[1] <= 2);
+    }
+
+    #[test]
+    fn test_find_with_progress() {
+        // The line is solved one node per layer, leaving one fewer
+        // each round.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let mut reports = Vec::new();
+        let mut on_layer = |k: usize, solved: usize, remaining: usize| {
+            reports.push((k, solved, remaining));
+        };
+        let result =
+            find_with_progress(g, nodeset([0]), nodeset([2]), pplane, &mut on_layer);
+        assert!(result.is_some());
+        assert_eq!(reports, vec![(1, 1, 1), (2, 1, 0)]);
+    }
+
+    #[test]
+    fn test_find_with_interrupt_cancelled() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let cancel = std::sync::atomic::AtomicBool::new(true);
+        let interrupt = Interrupt {
+            deadline: None,
+            cancel: Some(&cancel),
+        };
+        let result = find_with_interrupt(g, nodeset([0]), nodeset([2]), pplane, interrupt);
+        assert_eq!(result, Err(Interrupted));
+    }
+
+    #[test]
+    fn test_find_with_interrupt_untriggered() {
+        // Without deadline or flag the search matches `find`.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let result = find_with_interrupt(
+            g.clone(),
+            nodeset([0]),
+            nodeset([2]),
+            pplane.clone(),
+            Interrupt::default(),
+        )
+        .unwrap();
+        assert_eq!(result, find(g, nodeset([0]), nodeset([2]), pplane));
+    }
+
+    #[test]
+    fn test_find_with_branch_report() {
+        // The Pauli-Y node 0 ends up in the YZ branch; the XY node 2
+        // keeps its plane's branch.
+        let g = test_utils::graph(3, &[(1, 2)]);
+        let pplane = pplanes([(0, PPlane::Y), (2, PPlane::XY)]);
+        let (f, _, branch) =
+            find_with_branch_report(g, nodeset([]), nodeset([1]), pplane).unwrap();
+        assert_eq!(f[&0], nodeset([0]));
+        assert_eq!(branch[&0], Branch::YZ);
+        assert_eq!(branch[&2], Branch::XY);
+    }
+
+    #[test]
+    fn test_contains_self() {
+        // A node is in its own correction set exactly when its branch
+        // says so: the Pauli-Y node 0 lands in YZ and self-corrects,
+        // the plane-XY node 2 never does, and the XZ node 3 always
+        // does.
+        let g = test_utils::graph(5, &[(1, 2), (3, 4)]);
+        let pplane = pplanes([(0, PPlane::Y), (2, PPlane::XY), (3, PPlane::XZ)]);
+        let (f, _, branch) =
+            find_with_branch_report(g, nodeset([]), nodeset([1, 4]), pplane).unwrap();
+        for (&u, b) in &branch {
+            assert_eq!(f[&u].contains(&u), b.contains_self(), "node {u}");
+        }
+        assert!(Branch::XZ.contains_self());
+        assert!(Branch::YZ.contains_self());
+        assert!(!Branch::XY.contains_self());
+    }
+
+    #[test]
+    fn test_find_structured() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let result =
+            find_structured(g.clone(), nodeset([0]), nodeset([2]), pplane.clone()).unwrap();
+        let (f, layer) = find(g, nodeset([0]), nodeset([2]), pplane).unwrap();
+        assert_eq!(result.f, f);
+        assert_eq!(result.layer, layer);
+        assert_eq!(result.buckets, vec![vec![2], vec![1], vec![0]]);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.branch[&0], Branch::XY);
+        assert_eq!(result.branch[&1], Branch::XY);
+    }
+
+    #[test]
+    fn test_find_with_stats() {
+        // The Pauli-Y node 0 fails XY and XZ before YZ succeeds; the
+        // XY node 2 succeeds immediately.
+        let g = test_utils::graph(3, &[(1, 2)]);
+        let pplane = pplanes([(0, PPlane::Y), (2, PPlane::XY)]);
+        let (f, _, stats) =
+            find_with_stats(g, nodeset([]), nodeset([1]), pplane).unwrap();
+        assert_eq!(f[&0], nodeset([0]));
+        assert_eq!(stats[&Branch::XY].attempts, 2);
+        assert_eq!(stats[&Branch::XY].successes, 1);
+        assert_eq!(stats[&Branch::XZ].attempts, 1);
+        assert_eq!(stats[&Branch::XZ].successes, 0);
+        assert_eq!(stats[&Branch::YZ].attempts, 1);
+        assert_eq!(stats[&Branch::YZ].successes, 1);
+    }
+
+    #[test]
+    fn test_find_with_node_stats() {
+        // Same instance as test_find_with_stats, viewed per node: the
+        // Pauli-Y node 0 tries XY and XZ before YZ succeeds, the XY
+        // node 2 succeeds on its only branch.
+        let g = test_utils::graph(3, &[(1, 2)]);
+        let pplane = pplanes([(0, PPlane::Y), (2, PPlane::XY)]);
+        let (f, _, node_stats) =
+            find_with_node_stats(g, nodeset([]), nodeset([1]), pplane).unwrap();
+        assert_eq!(f[&0], nodeset([0]));
+        assert_eq!(node_stats[&0].len(), 1);
+        assert_eq!(
+            node_stats[&0][0].attempted,
+            vec![(Branch::XY, false), (Branch::XZ, false), (Branch::YZ, true)]
+        );
+        assert_eq!(node_stats[&0][0].round, 1);
+        // Columns: only the output node 1 (node 0 excludes itself and
+        // the unprocessed XY node 2 is barred); rows: node 0 heading
+        // the other non-Z unprocessed nodes.
+        assert_eq!(node_stats[&0][0].cols, 1);
+        assert_eq!(node_stats[&0][0].rows, 2);
+        assert_eq!(node_stats[&2][0].attempted, vec![(Branch::XY, true)]);
+    }
+
+    #[test]
+    fn test_solve_single() {
+        // Replays the rounds of find on the line 0-1-2 one node at a
+        // time.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let (f, layer) = find(g.clone(), nodeset([0]), nodeset([2]), pplane.clone()).unwrap();
+        assert_eq!(layer, vec![2, 1, 0]);
+        // Round 1: node 1 is solvable, node 0 is not.
+        let (branch, fu, nullity) =
+            solve_single(&g, &nodeset([0]), &nodeset([2]), &pplane, 1, &nodeset([])).unwrap();
+        assert_eq!(branch, Branch::XY);
+        assert_eq!(fu, f[&1]);
+        assert_eq!(nullity, 0);
+        assert!(solve_single(&g, &nodeset([0]), &nodeset([2]), &pplane, 0, &nodeset([])).is_none());
+        // Round 2: with node 1 solved, node 0 follows.
+        let (_, fu, _) =
+            solve_single(&g, &nodeset([0]), &nodeset([2]), &pplane, 0, &nodeset([1])).unwrap();
+        assert_eq!(fu, f[&0]);
+    }
+
+    #[test]
+    fn test_find_with_branch_preference() {
+        // The Pauli-Y node 0 is solvable in both XY and YZ: the default
+        // order picks XY, preferring YZ flips the choice (and the
+        // correction set) without touching the layering.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let pplane = pplanes([(0, PPlane::Y)]);
+        let (f, layer, branch) =
+            find_with_branch_report(g.clone(), nodeset([]), nodeset([1]), pplane.clone()).unwrap();
+        assert_eq!(branch[&0], Branch::XY);
+        assert_eq!(f[&0], nodeset([1]));
+        let preference = HashMap::from([(0, vec![Branch::YZ])]);
+        let (f2, layer2, branch2) =
+            find_with_branch_preference(g, nodeset([]), nodeset([1]), pplane, &preference).unwrap();
+        assert_eq!(branch2[&0], Branch::YZ);
+        assert_eq!(f2[&0], nodeset([0]));
+        assert_eq!(layer2, layer);
+    }
+
+    #[test]
+    fn test_find_with_branches_forced_ok() {
+        // Forcing the YZ branch for the Pauli-Z node still succeeds.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let pplane = pplanes([(0, PPlane::Z)]);
+        let forced = HashMap::from([(0, Branch::YZ)]);
+        let (f, _) =
+            find_with_branches(g, nodeset([]), nodeset([1]), pplane, &forced).unwrap();
+        // YZ: node 0 corrects through itself without touching its odd
+        // neighborhood.
+        assert_eq!(f[&0], nodeset([0]));
+    }
+
+    #[test]
+    fn test_find_with_branches_forced_fails() {
+        // XY is not admitted for a Pauli-Z node, so forcing it fails.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let pplane = pplanes([(0, PPlane::Z)]);
+        let forced = HashMap::from([(0, Branch::XY)]);
+        assert!(find_with_branches(g, nodeset([]), nodeset([1]), pplane, &forced).is_none());
+    }
+
+    #[test]
+    fn test_find_with_budget() {
+        // 0 - 1 - 2 needs the two correctors 1 and 2; budget 1 starves
+        // the search while budget 2 leaves the flow untouched.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        assert!(
+            find_with_budget(g.clone(), nodeset([0]), nodeset([2]), pplane.clone(), 1).is_none()
+        );
+        let (f, layer) =
+            find_with_budget(g, nodeset([0]), nodeset([2]), pplane, 2).unwrap();
+        assert_eq!(f[&0], nodeset([1]));
+        assert_eq!(f[&1], nodeset([2]));
+        assert_eq!(layer, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_verify_accepts_finder_output() {
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::X)]);
+        let (f, layer) =
+            find(g.clone(), nodeset([0]), nodeset([2]), pplane.clone()).unwrap();
+        verify(&g, &nodeset([0]), &nodeset([2]), &pplane, &f, &layer).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_correction() {
+        // {2} does not have 0 in its odd neighborhood.
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let f = PFlow::from([(0, nodeset([2])), (1, nodeset([2]))]);
+        let err = verify(&g, &nodeset([0]), &nodeset([2]), &pplane, &f, &vec![2, 1, 0])
+            .unwrap_err();
+        assert!(err.to_string().contains("plane condition violated: 0"));
+    }
+
+    #[test]
+    fn test_find_with_nullity() {
+        // Node 0 can be corrected by {1} or {2}, one bit of slack; on
+        // the line both corrections are forced.
+        let g = test_utils::graph(3, &[(0, 1), (0, 2)]);
+        let pplane = pplanes([(0, PPlane::XY)]);
+        let (_, _, nullity) =
+            find_with_nullity(g, nodeset([]), nodeset([1, 2]), pplane).unwrap();
+        assert_eq!(nullity[&0], 1);
+        let g = test_utils::graph(3, &[(0, 1), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        let (_, _, nullity) =
+            find_with_nullity(g, nodeset([0]), nodeset([2]), pplane).unwrap();
+        // Node 1 is forced to {2}; node 0 may add the output to {1}
+        // once 1 is solved, one bit of slack.
+        assert_eq!(nullity[&1], 0);
+        assert_eq!(nullity[&0], 1);
+    }
+
+    #[test]
+    fn test_find_z_measurement() {
+        // A Pauli-Z node is corrected through itself.
+        let g = test_utils::graph(2, &[(0, 1)]);
+        let pplane = pplanes([(0, PPlane::Z)]);
+        let (f, layer) = find(g, nodeset([]), nodeset([1]), pplane).unwrap();
+        assert!(f[&0].contains(&0));
+        assert_eq!(layer, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_find_none() {
+        // Isolated XY-measured node cannot be corrected.
+        let g = test_utils::graph(2, &[]);
+        let pplane = pplanes([(0, PPlane::XY)]);
+        assert!(find(g, nodeset([]), nodeset([1]), pplane).is_none());
+    }
+
+    #[test]
+    fn test_find_isolated_node() {
+        // The isolated XY node 2 can never sit in its own odd
+        // neighborhood, so the search fails up front even though the
+        // 0-1 pair alone has a fine flow; an isolated Pauli-Z node
+        // corrects itself through the YZ branch and is no obstacle.
+        let g = test_utils::graph(3, &[(0, 1)]);
+        let pplane = pplanes([(0, PPlane::XY), (2, PPlane::XY)]);
+        assert!(find(g.clone(), nodeset([]), nodeset([1]), pplane).is_none());
+        let pplane = pplanes([(0, PPlane::XY), (2, PPlane::Z)]);
+        let (f, layer) = find(g, nodeset([]), nodeset([1]), pplane).unwrap();
+        assert_eq!(f[&2], nodeset([2]));
+        assert_eq!(layer, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn test_find_more_inputs_than_outputs() {
+        // Two inputs but one output: rejected before the first round.
+        let g = test_utils::graph(3, &[(0, 2), (1, 2)]);
+        let pplane = pplanes([(0, PPlane::XY), (1, PPlane::XY)]);
+        assert!(find(g, nodeset([0, 1]), nodeset([2]), pplane).is_none());
+    }
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by the unit tests.
+
+use std::collections::HashMap;
+
+use crate::common::{Graph, Nodes};
+use crate::gflow::Plane;
+use crate::pflow::PPlane;
+
+/// Builds a graph on `n` nodes from an undirected edge list.
+pub fn graph(n: usize, edges: &[(usize, usize)]) -> Graph {
+    let mut g = vec![Nodes::new(); n];
+    for &(u, v) in edges {
+        g[u].insert(v);
+        g[v].insert(u);
+    }
+    g
+}
+
+/// Builds a node set from a list of indices.
+pub fn nodeset(nodes: impl IntoIterator<Item = usize>) -> Nodes {
+    nodes.into_iter().collect()
+}
+
+/// Builds a measurement-plane assignment.
+pub fn planes(it: impl IntoIterator<Item = (usize, Plane)>) -> HashMap<usize, Plane> {
+    it.into_iter().collect()
+}
+
+/// Builds a measurement-pplane assignment.
+pub fn pplanes(it: impl IntoIterator<Item = (usize, PPlane)>) -> HashMap<usize, PPlane> {
+    it.into_iter().collect()
+}